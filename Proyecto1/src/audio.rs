@@ -1,14 +1,21 @@
 use rodio::{Decoder, OutputStream, Sink};
 use std::fs::File;
 use std::io::BufReader;
-use std::time::{Duration, Instant};
+
+const DEFAULT_MUSIC: &str = "assets/sounds/music.mp3";
+//Segundos quieto antes de cortar el sonido de pasos (evita cortes entre pasos de simulación)
+const RUNNING_STOP_DELAY: f32 = 0.1;
+const MUSIC_VOLUME: f32 = 0.3;
+const RUNNING_VOLUME: f32 = 0.8;
 
 pub struct AudioManager {
     pub _stream: OutputStream,
     pub music_sink: Sink,
     pub sfx_sink: Sink,
     pub running_sink: Sink,
-    last_running_sound: Instant,
+    still_time: f32,
+    music_path: String,
+    volume: f32, //Volumen general que escala música y efectos
 }
 
 impl AudioManager {
@@ -23,15 +30,33 @@ impl AudioManager {
             music_sink,
             sfx_sink,
             running_sink,
-            last_running_sound: Instant::now(),
+            still_time: 0.0,
+            music_path: DEFAULT_MUSIC.to_string(),
+            volume: 1.0,
         })
     }
     
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.music_sink.set_volume(MUSIC_VOLUME * self.volume);
+        self.running_sink.set_volume(RUNNING_VOLUME * self.volume);
+    }
+    
+    //Cambiar la música de fondo (por ejemplo, la declarada por un nivel)
+    pub fn set_background_music(&mut self, path: Option<&str>) {
+        let path = path.unwrap_or(DEFAULT_MUSIC);
+        if self.music_path != path {
+            self.music_path = path.to_string();
+            self.music_sink.stop();
+            self.play_background_music();
+        }
+    }
+    
     pub fn play_background_music(&self) {
-        if let Ok(file) = File::open("assets/sounds/music.mp3") {
+        if let Ok(file) = File::open(&self.music_path) {
             if let Ok(source) = Decoder::new(BufReader::new(file)) {
                 self.music_sink.append(source);
-                self.music_sink.set_volume(0.3);
+                self.music_sink.set_volume(MUSIC_VOLUME * self.volume);
                 self.music_sink.play();
             }
         }
@@ -43,6 +68,21 @@ impl AudioManager {
         }
     }
     
+    //Actualización por frame con el tiempo real transcurrido
+    pub fn update(&mut self, dt: f32, moving: bool) {
+        self.maintain_background_music();
+        
+        if moving {
+            self.still_time = 0.0;
+            self.play_running_sound();
+        } else {
+            self.still_time += dt;
+            if self.still_time >= RUNNING_STOP_DELAY {
+                self.stop_running_sound();
+            }
+        }
+    }
+    
     pub fn play_running_sound(&mut self) {
         if self.running_sink.empty() {
             match File::open("assets/sounds/running.mp3") {
@@ -50,7 +90,7 @@ impl AudioManager {
                     match Decoder::new(BufReader::new(file)) {
                         Ok(source) => {
                             self.running_sink.append(source);
-                            self.running_sink.set_volume(0.8);
+                            self.running_sink.set_volume(RUNNING_VOLUME * self.volume);
                             self.running_sink.play();
                         },
                         Err(e) => println!("Error decodificando running.mp3: {}", e),
@@ -88,7 +128,7 @@ impl AudioManager {
                 //Limpiar efectos anteriores si están sonando
                 self.sfx_sink.stop();
                 self.sfx_sink.append(source);
-                self.sfx_sink.set_volume(volume);
+                self.sfx_sink.set_volume(volume * self.volume);
                 self.sfx_sink.play();
             }
         }
@@ -1,69 +1,303 @@
-use raylib::color::Color;
+use raylib::prelude::*;
 use crate::framebuffer::Framebuffer;
+use crate::line::line;
 use crate::maze::Maze;
 use crate::player::Player;
+use crate::doors::{Doors, is_door};
+
+//Cara de la celda que golpeó el rayo (el norte es hacia y negativo)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallFace {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl WallFace {
+    //Factor de brillo por orientación: las caras norte/sur se ven más oscuras
+    pub fn shade(&self) -> f32 {
+        match self {
+            WallFace::East | WallFace::West => 1.0,
+            WallFace::North | WallFace::South => 0.7,
+        }
+    }
+}
 
 pub struct Intersect {
     pub distance: f32,
     pub impact: char,
-    pub tx: f32, 
+    pub tx: f32,
+    pub face: WallFace,
+    pub hit: Vector2,
+    pub cell: (i32, i32),
+}
+
+//Distancia de la cámara al plano de proyección para que el FOV cubra todo el ancho
+pub fn projection_plane_distance(fov: f32, screen_width: u32) -> f32 {
+    (screen_width as f32 / 2.0) / (fov / 2.0).tan()
+}
+
+//Ángulo del rayo que pasa por el centro de la columna indicada del plano de proyección
+pub fn column_angle(player: &Player, column: u32, screen_width: u32) -> f32 {
+    let plane_distance = projection_plane_distance(player.fov, screen_width);
+    let offset = column as f32 + 0.5 - screen_width as f32 / 2.0;
+    player.a + (offset / plane_distance).atan()
 }
 
 pub fn cast_ray(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
+    doors: &Doors,
     player: &Player,
     a: f32,
     block_size: usize,
     draw_line: bool,
 ) -> Intersect {
-    let mut d = 0.0;
-    let step_size = 1.0;
-    let max_distance = 1000.0;
-    
-    framebuffer.set_current_color(Color::WHITESMOKE);
-    
+    let intersect = cast_ray_from(maze, doors, player.pos, a, block_size);
+
+    //Dibujar línea si es necesario
+    if draw_line {
+        framebuffer.set_current_color(Color::WHITESMOKE);
+        let end = Vector2::new(
+            player.pos.x + intersect.distance * a.cos(),
+            player.pos.y + intersect.distance * a.sin(),
+        );
+        line(framebuffer, player.pos, end);
+    }
+
+    intersect
+}
+
+//Recorrido DDA: avanza celda por celda sobre la cuadrícula en lugar de muestrear
+//el rayo cada unidad, así la distancia y el punto de impacto son exactos.
+//Las puertas se dibujan a mitad de su celda y el rayo pasa por la parte ya abierta
+pub fn cast_ray_from(
+    maze: &Maze,
+    doors: &Doors,
+    origin: Vector2,
+    a: f32,
+    block_size: usize,
+) -> Intersect {
+    trace_ray(maze, doors, origin, a, block_size, f32::INFINITY, |_| {})
+}
+
+//Igual que cast_ray_from, avisando cada celda que el rayo atraviesa: la de origen,
+//las libres y la del impacto (puede quedar fuera del laberinto). Si no golpea nada
+//antes de max_distance se detiene ahí con impact ' ', sin visitar las celdas siguientes
+pub fn trace_ray(
+    maze: &Maze,
+    doors: &Doors,
+    origin: Vector2,
+    a: f32,
+    block_size: usize,
+    max_distance: f32,
+    mut visit: impl FnMut((i32, i32)),
+) -> Intersect {
+    let block = block_size as f32;
+    let dir_x = a.cos();
+    let dir_y = a.sin();
+
+    //Posición en unidades de celda
+    let pos_x = origin.x / block;
+    let pos_y = origin.y / block;
+    let mut i = pos_x.floor() as i32;
+    let mut j = pos_y.floor() as i32;
+
+    //Distancia (en celdas) que recorre el rayo para cruzar una celda completa en cada eje
+    let delta_x = if dir_x == 0.0 { f32::INFINITY } else { (1.0 / dir_x).abs() };
+    let delta_y = if dir_y == 0.0 { f32::INFINITY } else { (1.0 / dir_y).abs() };
+
+    let (step_x, mut side_x) = if dir_x < 0.0 {
+        (-1, (pos_x - i as f32) * delta_x)
+    } else {
+        (1, (i as f32 + 1.0 - pos_x) * delta_x)
+    };
+    let (step_y, mut side_y) = if dir_y < 0.0 {
+        (-1, (pos_y - j as f32) * delta_y)
+    } else {
+        (1, (j as f32 + 1.0 - pos_y) * delta_y)
+    };
+    visit((i, j));
+
     loop {
-        let cos = d * a.cos();
-        let sin = d * a.sin();
-        let x = (player.pos.x + cos) as usize;
-        let y = (player.pos.y + sin) as usize;
-        
-        let i = x / block_size;
-        let j = y / block_size;
-        
-        //Verificar límites del laberinto
-        if j >= maze.len() || i >= maze[0].len() || d > max_distance {
+        //Avanzar hacia el borde de celda más cercano
+        let (t, face) = if side_x < side_y {
+            let t = side_x;
+            side_x += delta_x;
+            i += step_x;
+            (t, if step_x > 0 { WallFace::West } else { WallFace::East })
+        } else {
+            let t = side_y;
+            side_y += delta_y;
+            j += step_y;
+            (t, if step_y > 0 { WallFace::North } else { WallFace::South })
+        };
+        let distance = t * block;
+        if distance > max_distance {
             return Intersect {
-                distance: d,
-                impact: '+', //Pared por defecto
+                distance: max_distance,
+                impact: ' ', //Nada a la vista
                 tx: 0.0,
+                face,
+                hit: Vector2::new(origin.x + max_distance * dir_x, origin.y + max_distance * dir_y),
+                cell: (i, j),
             };
         }
-        
-        //Verificar si golpeamos una pared
-        if maze[j][i] != ' ' {
-            //Cálculo simplificado de coordenada de textura
-            let hit_x = player.pos.x + cos;
-            let hit_y = player.pos.y + sin;
-            
-            //Usar una coordenada de textura simple y estable
-            let tx = ((hit_x + hit_y) / block_size as f32).fract();
-            
+        let hit = Vector2::new(origin.x + distance * dir_x, origin.y + distance * dir_y);
+        visit((i, j));
+
+        //Verificar límites del laberinto
+        if j < 0 || i < 0 || j as usize >= maze.len() || i as usize >= maze[j as usize].len() {
             return Intersect {
-                distance: d,
-                impact: maze[j][i],
-                tx: tx.abs(), // Asegurar que sea positivo
+                distance,
+                impact: '+', //Pared por defecto
+                tx: face_tx(face, hit, block),
+                face,
+                hit,
+                cell: (i, j),
             };
         }
-        
-        //Dibujar línea si es necesario
-        if draw_line {
-            if x < framebuffer.width as usize && y < framebuffer.height as usize {
-                framebuffer.set_pixel(x as u32, y as u32);
+
+        //Puerta: plano en el medio de la celda; la hoja se desliza dejando libre [0, open)
+        let cell = maze[j as usize][i as usize];
+        if is_door(cell) {
+            let door = doors.get((i as usize, j as usize));
+            let open = door.map_or(0.0, |door| door.open);
+            let horizontal = door.is_none_or(|door| door.horizontal);
+            //El rayo sale de la celda en el siguiente borde que cruzaría
+            let exit = side_x.min(side_y);
+
+            let (plane_t, along, door_face) = if horizontal {
+                let plane_t = (j as f32 + 0.5 - pos_y) / dir_y;
+                (plane_t, pos_x + plane_t * dir_x - i as f32, if dir_y > 0.0 { WallFace::North } else { WallFace::South })
+            } else {
+                let plane_t = (i as f32 + 0.5 - pos_x) / dir_x;
+                (plane_t, pos_y + plane_t * dir_y - j as f32, if dir_x > 0.0 { WallFace::West } else { WallFace::East })
+            };
+
+            //Una división por cero da infinito o NaN y no pasa estas comparaciones
+            if plane_t >= t && plane_t <= exit && along >= open && along <= 1.0 {
+                let distance = plane_t * block;
+                return Intersect {
+                    distance,
+                    impact: cell,
+                    tx: along - open, //La textura se mueve con la hoja
+                    face: door_face,
+                    hit: Vector2::new(origin.x + distance * dir_x, origin.y + distance * dir_y),
+                    cell: (i, j),
+                };
             }
+            continue;
         }
-        
-        d += step_size;
+
+        //Verificar si golpeamos una pared
+        if cell != ' ' {
+            return Intersect {
+                distance,
+                impact: cell,
+                tx: face_tx(face, hit, block),
+                face,
+                hit,
+                cell: (i, j),
+            };
+        }
+    }
+}
+
+//Coordenada de textura a lo largo de la cara golpeada, espejada para que la
+//textura siempre se lea de izquierda a derecha desde el punto de vista del jugador
+fn face_tx(face: WallFace, hit: Vector2, block: f32) -> f32 {
+    let along = match face {
+        WallFace::West | WallFace::East => hit.y / block,
+        WallFace::North | WallFace::South => hit.x / block,
+    };
+    let tx = along - along.floor();
+
+    match face {
+        WallFace::West | WallFace::South => tx,
+        WallFace::East | WallFace::North => 1.0 - tx,
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    const BLOCK_SIZE: usize = 100;
+
+    //Cada pared tiene un carácter distinto para saber cuál golpeó el rayo
+    fn room() -> Maze {
+        ["+-N-+", "W   E", "|   R", "+-S-+"].iter().map(|row| row.chars().collect()).collect()
+    }
+
+    fn cast(origin: (f32, f32), a: f32) -> Intersect {
+        cast_ray_from(&room(), &Doors::default(), Vector2::new(origin.0, origin.1), a, BLOCK_SIZE)
+    }
+
+    fn assert_hit(origin: (f32, f32), a: f32, distance: f32, impact: char, cell: (i32, i32)) {
+        let hit = cast(origin, a);
+        assert_eq!(hit.impact, impact);
+        assert_eq!(hit.cell, cell);
+        assert!((hit.distance - distance).abs() < 0.01, "distancia {} en lugar de {}", hit.distance, distance);
+    }
+
+    #[test]
+    fn axis_aligned_rays() {
+        //Desde el centro de la celda (2, 1)
+        assert_hit((250.0, 150.0), 0.0, 150.0, 'E', (4, 1));
+        assert_hit((250.0, 150.0), PI, 150.0, 'W', (0, 1));
+        assert_hit((250.0, 150.0), -PI / 2.0, 50.0, 'N', (2, 0));
+        assert_hit((250.0, 150.0), PI / 2.0, 150.0, 'S', (2, 3));
+    }
+
+    #[test]
+    fn diagonal_ray() {
+        //Pendiente 1/2 desde (1.5, 1.2): entra en (3, 2) y choca con la pared derecha en (4, 2.45)
+        let a = 0.5f32.atan();
+        assert_hit((150.0, 120.0), a, 2.5f32.hypot(1.25) * BLOCK_SIZE as f32, 'R', (4, 2));
+        let hit = cast((150.0, 120.0), a);
+        assert!((hit.hit.x - 400.0).abs() < 0.01 && (hit.hit.y - 245.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn leaving_the_maze_hits_a_default_wall() {
+        let maze: Maze = vec![vec![' '; 3]];
+        let hit = cast_ray_from(&maze, &Doors::default(), Vector2::new(50.0, 50.0), 0.0, BLOCK_SIZE);
+        assert_eq!(hit.impact, '+');
+        assert_eq!(hit.cell, (3, 0));
+        assert!((hit.distance - 250.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn faces_and_mirrored_tx() {
+        //Rayo hacia el este: golpea la cara oeste de la pared
+        let hit = cast((250.0, 120.0), 0.0);
+        assert_eq!(hit.face, WallFace::West);
+        assert!((hit.tx - 0.2).abs() < 0.001);
+
+        //Hacia el oeste la cara este se espeja
+        let hit = cast((250.0, 120.0), PI);
+        assert_eq!(hit.face, WallFace::East);
+        assert!((hit.tx - 0.8).abs() < 0.001);
+
+        //Hacia el norte golpea la cara sur, hacia el sur la cara norte espejada
+        let hit = cast((230.0, 150.0), -PI / 2.0);
+        assert_eq!(hit.face, WallFace::South);
+        assert!((hit.tx - 0.3).abs() < 0.001);
+
+        let hit = cast((230.0, 150.0), PI / 2.0);
+        assert_eq!(hit.face, WallFace::North);
+        assert!((hit.tx - 0.7).abs() < 0.001);
+    }
+
+    #[test]
+    fn face_tx_wraps_per_cell() {
+        let block = BLOCK_SIZE as f32;
+        assert!((face_tx(WallFace::West, Vector2::new(400.0, 275.0), block) - 0.75).abs() < 0.001);
+        assert!((face_tx(WallFace::East, Vector2::new(100.0, 275.0), block) - 0.25).abs() < 0.001);
+        assert!((face_tx(WallFace::South, Vector2::new(310.0, 100.0), block) - 0.1).abs() < 0.001);
+        assert!((face_tx(WallFace::North, Vector2::new(310.0, 300.0), block) - 0.9).abs() < 0.001);
+    }
+}
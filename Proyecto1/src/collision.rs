@@ -0,0 +1,142 @@
+use raylib::prelude::*;
+use crate::level::is_walkable;
+use crate::maze::Maze;
+use crate::doors::{Doors, is_door};
+
+//Celda sólida para colisiones; fuera del laberinto todo cuenta como pared.
+//Una puerta solo deja pasar cuando está abierta del todo
+fn is_solid(maze: &Maze, doors: &Doors, i: i32, j: i32) -> bool {
+    if i < 0 || j < 0 || j as usize >= maze.len() || i as usize >= maze[j as usize].len() {
+        return true;
+    }
+    let cell = maze[j as usize][i as usize];
+    if is_door(cell) {
+        return !doors.is_open((i as usize, j as usize));
+    }
+    !is_walkable(cell)
+}
+
+//Verifica un círculo contra todas las celdas que toca su caja envolvente,
+//usando el punto de cada celda más cercano al centro
+pub fn circle_collides(maze: &Maze, doors: &Doors, center: Vector2, radius: f32, block_size: usize) -> bool {
+    let block = block_size as f32;
+    let min_i = ((center.x - radius) / block).floor() as i32;
+    let max_i = ((center.x + radius) / block).floor() as i32;
+    let min_j = ((center.y - radius) / block).floor() as i32;
+    let max_j = ((center.y + radius) / block).floor() as i32;
+
+    for j in min_j..=max_j {
+        for i in min_i..=max_i {
+            if !is_solid(maze, doors, i, j) {
+                continue;
+            }
+
+            let closest_x = center.x.clamp(i as f32 * block, (i + 1) as f32 * block);
+            let closest_y = center.y.clamp(j as f32 * block, (j + 1) as f32 * block);
+            let dx = center.x - closest_x;
+            let dy = center.y - closest_y;
+            if dx * dx + dy * dy < radius * radius {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+//Mueve un círculo resolviendo cada eje por separado: si un eje choca se descarta
+//solo ese componente, así el movimiento diagonal se desliza a lo largo de la pared.
+//Sirve para el jugador y para cualquier sprite que se mueva
+pub fn move_circle(maze: &Maze, doors: &Doors, position: Vector2, delta: Vector2, radius: f32, block_size: usize) -> Vector2 {
+    //Subdividir desplazamientos grandes para no atravesar paredes delgadas
+    let length = (delta.x * delta.x + delta.y * delta.y).sqrt();
+    let steps = (length / (radius * 0.5).max(1.0)).ceil().max(1.0) as usize;
+    let step = Vector2::new(delta.x / steps as f32, delta.y / steps as f32);
+
+    let mut position = position;
+    for _ in 0..steps {
+        let moved_x = Vector2::new(position.x + step.x, position.y);
+        if !circle_collides(maze, doors, moved_x, radius, block_size) {
+            position = moved_x;
+        }
+
+        let moved_y = Vector2::new(position.x, position.y + step.y);
+        if !circle_collides(maze, doors, moved_y, radius, block_size) {
+            position = moved_y;
+        }
+    }
+
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Inventory;
+
+    const BLOCK_SIZE: usize = 100;
+    const RADIUS: f32 = 10.0;
+
+    fn maze(rows: &[&str]) -> Maze {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn diagonal_slides_along_wall() {
+        let maze = maze(&["+-----+", "|     |", "|     |", "+-----+"]);
+        let doors = Doors::default();
+        //Pegado a la pared de arriba y moviéndose en diagonal hacia ella
+        let start = Vector2::new(250.0, 115.0);
+        let end = move_circle(&maze, &doors, start, Vector2::new(30.0, -30.0), RADIUS, BLOCK_SIZE);
+
+        //El componente a lo largo de la pared se conserva entero
+        assert!((end.x - 280.0).abs() < 0.001, "{:?}", end);
+        //El que va contra la pared se detiene antes de tocarla
+        assert!(end.y >= 100.0 + RADIUS && end.y <= start.y, "{:?}", end);
+        assert!(!circle_collides(&maze, &doors, end, RADIUS, BLOCK_SIZE));
+    }
+
+    #[test]
+    fn large_delta_does_not_tunnel() {
+        let maze = maze(&["+-------+", "|  |    |", "+-------+"]);
+        let doors = Doors::default();
+        let end = move_circle(&maze, &doors, Vector2::new(150.0, 150.0), Vector2::new(500.0, 0.0), RADIUS, BLOCK_SIZE);
+        //La pared de una celda en x = 3 frena el círculo
+        assert!(end.x <= 300.0 - RADIUS, "{:?}", end);
+        assert!(end.x > 250.0, "{:?}", end);
+    }
+
+    #[test]
+    fn outside_the_maze_is_solid() {
+        let maze = maze(&["   ", "   ", "   "]);
+        let doors = Doors::default();
+        assert!(is_solid(&maze, &doors, -1, 0));
+        assert!(is_solid(&maze, &doors, 0, -1));
+        assert!(is_solid(&maze, &doors, 3, 0));
+        assert!(is_solid(&maze, &doors, 0, 3));
+        assert!(!is_solid(&maze, &doors, 1, 1));
+
+        assert!(circle_collides(&maze, &doors, Vector2::new(-50.0, 150.0), RADIUS, BLOCK_SIZE));
+        //Sin paredes en la cuadrícula el borde del laberinto igual detiene el movimiento
+        let end = move_circle(&maze, &doors, Vector2::new(50.0, 150.0), Vector2::new(-100.0, 0.0), RADIUS, BLOCK_SIZE);
+        assert!(end.x >= RADIUS && end.x < 50.0, "{:?}", end);
+    }
+
+    #[test]
+    fn doors_are_solid_until_fully_open() {
+        let lines: Vec<String> = ["+-----+", "|  D g|", "+-----+"].iter().map(|row| row.to_string()).collect();
+        let level = crate::level::parse_level("test.txt", &lines).unwrap();
+        let mut doors = level.doors.clone();
+        assert!(is_solid(&level.maze, &doors, 3, 1));
+
+        let player = Vector2::new(250.0, 150.0);
+        doors.use_facing(&level.maze, player, 0.0, RADIUS, &mut Inventory::default(), BLOCK_SIZE);
+        doors.update(0.5);
+        assert!(is_solid(&level.maze, &doors, 3, 1));
+        assert_eq!(move_circle(&level.maze, &doors, player, Vector2::new(200.0, 0.0), RADIUS, BLOCK_SIZE).x, 300.0 - RADIUS);
+
+        doors.update(0.5);
+        assert!(!is_solid(&level.maze, &doors, 3, 1));
+        assert_eq!(move_circle(&level.maze, &doors, player, Vector2::new(200.0, 0.0), RADIUS, BLOCK_SIZE).x, 450.0);
+    }
+}
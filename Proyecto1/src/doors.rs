@@ -0,0 +1,301 @@
+use raylib::prelude::*;
+use std::collections::HashMap;
+use crate::maze::Maze;
+use crate::level::{Level, is_walkable};
+use crate::pathfinding::{Cell, world_to_cell};
+use crate::items::{Inventory, ItemKind};
+
+//Celdas interactivas de la cuadrícula
+pub const DOOR: char = 'D';
+pub const LOCKED_DOOR: char = 'L';
+pub const SWITCH: char = 'S';
+
+//Fracción de la puerta que se abre o cierra por segundo
+const DOOR_SPEED: f32 = 1.5;
+//Alcance para usar una puerta o un interruptor, en celdas desde el centro del jugador
+const USE_REACH: f32 = 1.0;
+
+pub fn is_door(cell: char) -> bool {
+    cell == DOOR || cell == LOCKED_DOOR
+}
+
+#[derive(Debug, Clone)]
+pub struct Door {
+    pub cell: Cell,
+    pub locked: bool,     //Necesita una llave la primera vez
+    pub remote: bool,     //Solo la abre un interruptor
+    pub horizontal: bool, //La hoja va de este a oeste (el pasillo corre de norte a sur)
+    pub open: f32,        //0.0 cerrada, 1.0 abierta del todo
+    opening: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Switch {
+    pub cell: Cell,
+    pub doors: Vec<Cell>,
+    pub on: bool,
+}
+
+//Resultado de intentar usar lo que el jugador tiene enfrente
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UseResult {
+    Nothing,
+    Door,     //Una puerta empezó a abrirse o cerrarse
+    Unlocked, //Se gastó una llave en una puerta con llave
+    Locked,   //Puerta con llave y ninguna llave
+    Remote,   //Puerta que solo abre un interruptor
+    Blocked,  //No se puede cerrar con el jugador adentro
+    Switch,   //Se accionó un interruptor
+}
+
+//Estado de las puertas e interruptores del nivel en curso
+#[derive(Debug, Clone, Default)]
+pub struct Doors {
+    doors: Vec<Door>,
+    switches: Vec<Switch>,
+    by_cell: HashMap<Cell, usize>,
+}
+
+impl Doors {
+    //Todas las puertas cerradas y los interruptores apagados
+    pub fn from_level(level: &Level) -> Self {
+        let maze = &level.maze;
+        let mut doors = Doors::default();
+
+        for (y, row) in maze.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if !is_door(cell) {
+                    continue;
+                }
+                //Con paredes a los lados la hoja cruza el pasillo de este a oeste
+                let solid = |nx: usize, ny: usize| maze.get(ny).and_then(|r| r.get(nx)).is_none_or(|&c| !is_walkable(c) && !is_door(c));
+                let horizontal = solid(x.wrapping_sub(1), y) && solid(x + 1, y);
+                doors.by_cell.insert((x, y), doors.doors.len());
+                doors.doors.push(Door {
+                    cell: (x, y),
+                    locked: cell == LOCKED_DOOR,
+                    remote: false,
+                    horizontal,
+                    open: 0.0,
+                    opening: false,
+                });
+            }
+        }
+
+        for (cell, targets) in &level.switches {
+            for target in targets {
+                if let Some(&index) = doors.by_cell.get(target) {
+                    doors.doors[index].remote = true;
+                }
+            }
+            doors.switches.push(Switch { cell: *cell, doors: targets.clone(), on: false });
+        }
+
+        doors
+    }
+
+    pub fn get(&self, cell: Cell) -> Option<&Door> {
+        self.by_cell.get(&cell).map(|&index| &self.doors[index])
+    }
+
+    pub fn switch_on(&self, cell: Cell) -> bool {
+        self.switches.iter().any(|s| s.cell == cell && s.on)
+    }
+
+    //Solo se puede pasar por una puerta abierta del todo
+    pub fn is_open(&self, cell: Cell) -> bool {
+        self.get(cell).is_some_and(|door| door.open >= 1.0)
+    }
+
+    //Anima las puertas que se están abriendo o cerrando
+    pub fn update(&mut self, dt: f32) {
+        for door in &mut self.doors {
+            let target = if door.opening { 1.0 } else { 0.0 };
+            if door.open < target {
+                door.open = (door.open + DOOR_SPEED * dt).min(1.0);
+            } else if door.open > target {
+                door.open = (door.open - DOOR_SPEED * dt).max(0.0);
+            }
+        }
+    }
+
+    //Usa la primera puerta o interruptor que haya frente al jugador; las puertas con llave
+    //gastan una del inventario
+    pub fn use_facing(&mut self, maze: &Maze, position: Vector2, angle: f32, radius: f32, inventory: &mut Inventory, block_size: usize) -> UseResult {
+        let block = block_size as f32;
+        let own_cell = world_to_cell(position, block_size);
+
+        //Avanzar en cuartos de celda hasta el alcance; una pared corta la búsqueda
+        let mut distance = 0.25 * block;
+        while distance <= USE_REACH * block {
+            let probe = Vector2::new(position.x + distance * angle.cos(), position.y + distance * angle.sin());
+            let cell = world_to_cell(probe, block_size);
+            distance += 0.25 * block;
+            if cell == own_cell {
+                continue;
+            }
+
+            let Some(&content) = maze.get(cell.1).and_then(|row| row.get(cell.0)) else {
+                return UseResult::Nothing;
+            };
+            if content == SWITCH {
+                return self.toggle_switch(cell, position, radius, block_size);
+            }
+            if is_door(content) {
+                return self.use_door(cell, position, radius, inventory, block_size);
+            }
+            if !is_walkable(content) {
+                return UseResult::Nothing;
+            }
+        }
+
+        UseResult::Nothing
+    }
+
+    fn use_door(&mut self, cell: Cell, position: Vector2, radius: f32, inventory: &mut Inventory, block_size: usize) -> UseResult {
+        let Some(&index) = self.by_cell.get(&cell) else {
+            return UseResult::Nothing;
+        };
+        let door = &self.doors[index];
+
+        if door.remote {
+            return UseResult::Remote;
+        }
+        if door.opening && overlaps_cell(position, radius, cell, block_size) {
+            return UseResult::Blocked;
+        }
+
+        let mut result = UseResult::Door;
+        if door.locked {
+            if !inventory.take(ItemKind::Key) {
+                return UseResult::Locked;
+            }
+            self.doors[index].locked = false;
+            result = UseResult::Unlocked;
+        }
+
+        let door = &mut self.doors[index];
+        door.opening = !door.opening;
+        result
+    }
+
+    //Cambia todas las puertas del grupo; si alguna no se puede cerrar el interruptor no se mueve
+    fn toggle_switch(&mut self, cell: Cell, position: Vector2, radius: f32, block_size: usize) -> UseResult {
+        let Some(switch) = self.switches.iter().find(|s| s.cell == cell) else {
+            return UseResult::Nothing;
+        };
+        let on = !switch.on;
+        let targets = switch.doors.clone();
+
+        if !on && targets.iter().any(|&door| overlaps_cell(position, radius, door, block_size)) {
+            return UseResult::Blocked;
+        }
+
+        for target in &targets {
+            if let Some(&index) = self.by_cell.get(target) {
+                let door = &mut self.doors[index];
+                door.locked = false;
+                door.opening = on;
+            }
+        }
+        if let Some(switch) = self.switches.iter_mut().find(|s| s.cell == cell) {
+            switch.on = on;
+        }
+        UseResult::Switch
+    }
+}
+
+//Si un círculo toca una celda
+fn overlaps_cell(center: Vector2, radius: f32, (x, y): Cell, block_size: usize) -> bool {
+    let block = block_size as f32;
+    let closest_x = center.x.clamp(x as f32 * block, (x + 1) as f32 * block);
+    let closest_y = center.y.clamp(y as f32 * block, (y + 1) as f32 * block);
+    let dx = center.x - closest_x;
+    let dy = center.y - closest_y;
+    dx * dx + dy * dy < radius * radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+    use crate::level::parse_level;
+
+    const BLOCK_SIZE: usize = 100;
+    const RADIUS: f32 = 20.0;
+
+    //Pasillo con una puerta en (3, 1) y un interruptor en la pared de abajo
+    fn doors(header: &str, door: char) -> (Level, Doors) {
+        let text = format!("#level 1\n{}\n[grid]\n+-----+\n|  {} g|\n+---S-+", header, door);
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let level = parse_level("test.txt", &lines).unwrap();
+        let doors = level.doors.clone();
+        (level, doors)
+    }
+
+    //Usar lo que haya al este desde x (en unidades del mundo), en la fila del pasillo
+    fn use_east(doors: &mut Doors, level: &Level, inventory: &mut Inventory, x: f32) -> UseResult {
+        doors.use_facing(&level.maze, Vector2::new(x, 150.0), 0.0, RADIUS, inventory, BLOCK_SIZE)
+    }
+
+    #[test]
+    fn locked_door_takes_one_key() {
+        let (level, mut doors) = doors("key = 1 1", LOCKED_DOOR);
+        let mut inventory = Inventory::default();
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 250.0), UseResult::Locked);
+
+        inventory.add(ItemKind::Key);
+        inventory.add(ItemKind::Key);
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 250.0), UseResult::Unlocked);
+        assert_eq!(inventory.count(ItemKind::Key), 1);
+        //Ya sin llave: cerrar y volver a abrir no gasta otra
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 250.0), UseResult::Door);
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 250.0), UseResult::Door);
+        assert_eq!(inventory.count(ItemKind::Key), 1);
+    }
+
+    #[test]
+    fn door_does_not_close_on_player() {
+        let (level, mut doors) = doors("", DOOR);
+        let mut inventory = Inventory::default();
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 250.0), UseResult::Door);
+        doors.update(1.0);
+
+        //El círculo del jugador ya entra en la celda de la puerta
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 290.0), UseResult::Blocked);
+        assert!(doors.is_open((3, 1)));
+    }
+
+    #[test]
+    fn switch_door_is_remote() {
+        let (level, mut doors) = doors("switch = 4 2 3 1", DOOR);
+        let mut inventory = Inventory::default();
+        assert_eq!(use_east(&mut doors, &level, &mut inventory, 250.0), UseResult::Remote);
+        assert_eq!(doors.get((3, 1)).unwrap().open, 0.0);
+
+        //Desde (4, 1) mirando al sur se acciona el interruptor
+        let result = doors.use_facing(&level.maze, Vector2::new(450.0, 150.0), PI / 2.0, RADIUS, &mut inventory, BLOCK_SIZE);
+        assert_eq!(result, UseResult::Switch);
+        assert!(doors.switch_on((4, 2)));
+        doors.update(1.0);
+        assert!(doors.is_open((3, 1)));
+    }
+
+    #[test]
+    fn open_only_when_fully_open() {
+        let (level, mut doors) = doors("", DOOR);
+        let mut inventory = Inventory::default();
+        use_east(&mut doors, &level, &mut inventory, 250.0);
+        doors.update(0.5);
+        assert!(!doors.is_open((3, 1)));
+        doors.update(0.5);
+        assert!(doors.is_open((3, 1)));
+
+        //Apenas empieza a cerrarse ya no se puede pasar
+        use_east(&mut doors, &level, &mut inventory, 250.0);
+        doors.update(0.1);
+        assert!(!doors.is_open((3, 1)));
+        //Una celda sin puerta nunca está abierta
+        assert!(!doors.is_open((1, 1)));
+    }
+}
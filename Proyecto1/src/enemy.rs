@@ -0,0 +1,188 @@
+use raylib::prelude::*;
+use std::collections::VecDeque;
+use crate::maze::Maze;
+use crate::pathfinding::{Cell, astar, walkable_cost, world_to_cell, cell_center};
+
+//Velocidades en unidades del mundo por segundo; el jugador camina a 75
+const PATROL_SPEED: f32 = 40.0;
+const CHASE_SPEED: f32 = 65.0;
+//Distancia máxima a la que un enemigo ve al jugador, en celdas
+pub const SIGHT_RANGE: f32 = 6.0;
+//Segundos que espera en cada punto de la ronda
+const IDLE_TIME: f32 = 1.5;
+//Segundos que sigue hacia la última posición conocida después de perder de vista al jugador
+const SEARCH_TIME: f32 = 2.5;
+//Segundos que ignora al jugador después de atraparlo
+const CATCH_COOLDOWN: f32 = 3.0;
+//Distancia entre centros a la que se considera que el enemigo alcanzó al jugador
+pub const CATCH_DISTANCE: f32 = 35.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnemyState {
+    Idle,   //Quieto en un punto de la ronda
+    Patrol, //Caminando hacia el siguiente punto de la ronda
+    Chase,  //Persiguiendo al jugador (o su última posición conocida)
+    Return, //Volviendo a la ronda después de perderlo
+}
+
+impl EnemyState {
+    pub fn get_name(&self) -> &str {
+        match self {
+            EnemyState::Idle => "idle",
+            EnemyState::Patrol => "patrol",
+            EnemyState::Chase => "chase",
+            EnemyState::Return => "return",
+        }
+    }
+}
+
+//Comportamiento de un sprite enemigo; la posición la sigue guardando el sprite
+#[derive(Debug, Clone)]
+pub struct Enemy {
+    pub state: EnemyState,
+    pub heading: f32, //Dirección en la que camina, para los marcadores del mapa
+    waypoints: Vec<Cell>, //Ronda de celdas; la primera es donde aparece
+    next_waypoint: usize,
+    path: VecDeque<Cell>, //Celdas que faltan para llegar al destino actual
+    target: Option<Cell>, //Celda a la que lleva el camino
+    timer: f32,    //Espera en Idle o búsqueda en Chase
+    cooldown: f32, //Tiempo sin ver al jugador después de atraparlo
+}
+
+impl Enemy {
+    pub fn new(waypoints: Vec<Cell>) -> Self {
+        Enemy {
+            state: EnemyState::Idle,
+            heading: 0.0,
+            waypoints,
+            next_waypoint: 0,
+            path: VecDeque::new(),
+            target: None,
+            timer: IDLE_TIME,
+            cooldown: 0.0,
+        }
+    }
+
+    //Un paso de la simulación. sees_player ya considera distancia y paredes
+    pub fn update(&mut self, position: &mut Vector2, player_pos: Vector2, sees_player: bool, maze: &Maze, block_size: usize, dt: f32) {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        let sees_player = sees_player && self.cooldown <= 0.0;
+
+        if sees_player {
+            self.state = EnemyState::Chase;
+            self.timer = SEARCH_TIME;
+            let player_cell = world_to_cell(player_pos, block_size);
+            if player_cell == world_to_cell(*position, block_size) {
+                self.path.clear();
+                self.target = None;
+            } else {
+                self.set_target(*position, player_cell, maze, block_size);
+            }
+        }
+
+        match self.state {
+            EnemyState::Idle => {
+                self.timer -= dt;
+                //Con un solo punto la ronda es una guardia fija
+                if self.timer <= 0.0 && self.waypoints.len() > 1 {
+                    self.next_waypoint = (self.next_waypoint + 1) % self.waypoints.len();
+                    self.state = EnemyState::Patrol;
+                    self.set_target(*position, self.waypoints[self.next_waypoint], maze, block_size);
+                }
+            },
+            EnemyState::Patrol | EnemyState::Return => {
+                if self.walk(position, PATROL_SPEED * dt, block_size) {
+                    self.state = EnemyState::Idle;
+                    self.timer = IDLE_TIME;
+                }
+            },
+            EnemyState::Chase => {
+                let arrived = self.walk(position, CHASE_SPEED * dt, block_size);
+                //En la misma celda que el jugador ya no hay paredes en medio: ir directo
+                if arrived && sees_player && world_to_cell(player_pos, block_size) == world_to_cell(*position, block_size) {
+                    let delta = player_pos - *position;
+                    let distance = delta.length();
+                    if distance > 0.0 {
+                        *position += delta * ((CHASE_SPEED * dt).min(distance) / distance);
+                        self.heading = delta.y.atan2(delta.x);
+                    }
+                }
+                if !sees_player {
+                    self.timer -= dt;
+                }
+                //Se perdió el rastro: volver al punto de la ronda al que iba
+                if !sees_player && (arrived || self.timer <= 0.0) {
+                    self.go_back(*position, maze, block_size);
+                }
+            },
+        }
+    }
+
+    //Recién después de atrapar al jugador no lo vuelve a atrapar
+    pub fn can_catch(&self) -> bool {
+        self.cooldown <= 0.0
+    }
+
+    //Después de atrapar al jugador lo deja en paz un rato y vuelve a su ronda
+    pub fn on_catch(&mut self, position: Vector2, maze: &Maze, block_size: usize) {
+        self.cooldown = CATCH_COOLDOWN;
+        self.go_back(position, maze, block_size);
+    }
+
+    fn go_back(&mut self, position: Vector2, maze: &Maze, block_size: usize) {
+        self.state = EnemyState::Return;
+        if let Some(&waypoint) = self.waypoints.get(self.next_waypoint) {
+            self.set_target(position, waypoint, maze, block_size);
+        }
+    }
+
+    //Calcula el camino hacia una celda; no hace nada si ya iba hacia ella
+    fn set_target(&mut self, position: Vector2, target: Cell, maze: &Maze, block_size: usize) {
+        if self.target == Some(target) {
+            return;
+        }
+
+        let start = world_to_cell(position, block_size);
+        let Some(path) = astar(maze, start, target, walkable_cost(maze)) else {
+            return;
+        };
+        let mut path = VecDeque::from(path);
+
+        //Volver al centro de la celda actual solo si el camino no sigue en la dirección
+        //en la que ya estaba caminando (así no retrocede al recalcular)
+        if let Some(&next) = path.get(1) {
+            let offset = position - cell_center(start, block_size);
+            let direction = cell_center(next, block_size) - cell_center(start, block_size);
+            let cross = offset.x * direction.y - offset.y * direction.x;
+            let dot = offset.x * direction.x + offset.y * direction.y;
+            if cross.abs() < block_size as f32 && dot >= 0.0 {
+                path.pop_front();
+            }
+        }
+
+        self.path = path;
+        self.target = Some(target);
+    }
+
+    //Avanza por el camino de centro a centro de celda; devuelve si llegó al final
+    fn walk(&mut self, position: &mut Vector2, mut step: f32, block_size: usize) -> bool {
+        while let Some(&cell) = self.path.front() {
+            let center = cell_center(cell, block_size);
+            let delta = center - *position;
+            let distance = delta.length();
+
+            if distance > step {
+                *position += delta * (step / distance);
+                self.heading = delta.y.atan2(delta.x);
+                return false;
+            }
+
+            *position = center;
+            step -= distance;
+            self.path.pop_front();
+        }
+
+        self.target = None;
+        true
+    }
+}
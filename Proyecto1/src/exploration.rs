@@ -0,0 +1,111 @@
+use crate::maze::Maze;
+use crate::doors::Doors;
+use crate::player::Player;
+use crate::level::is_passable;
+use crate::caster::{trace_ray, column_angle};
+use crate::pathfinding::Cell;
+
+//Celdas que el jugador ya vio en el nivel en curso; los mapas solo muestran estas
+//cuando el ajuste de mapa está en "explored"
+#[derive(Debug, Clone, Default)]
+pub struct Exploration {
+    seen: Vec<Vec<bool>>,
+}
+
+impl Exploration {
+    //Nada descubierto todavía
+    pub fn new(maze: &Maze) -> Self {
+        Exploration {
+            seen: maze.iter().map(|row| vec![false; row.len()]).collect(),
+        }
+    }
+
+    //Las celdas fuera del laberinto se ignoran
+    pub fn mark(&mut self, (x, y): (i32, i32)) {
+        if x < 0 || y < 0 {
+            return;
+        }
+        if let Some(seen) = self.seen.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+            *seen = true;
+        }
+    }
+
+    pub fn is_seen(&self, (x, y): Cell) -> bool {
+        self.seen.get(y).and_then(|row| row.get(x)).copied().unwrap_or(false)
+    }
+
+    //Recorre los mismos rayos que render_world, para descubrir celdas cuando no se dibuja en 3D
+    pub fn look(&mut self, maze: &Maze, doors: &Doors, player: &Player, block_size: usize, rays: u32) {
+        for i in 0..rays {
+            let a = column_angle(player, i, rays);
+            trace_ray(maze, doors, player.pos, a, block_size, f32::INFINITY, |cell| self.mark(cell));
+        }
+    }
+
+    //Porcentaje de celdas transitables (pasillos, meta y puertas) ya vistas
+    pub fn percent(&self, maze: &Maze) -> f32 {
+        let mut total = 0;
+        let mut seen = 0;
+        for (y, row) in maze.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if is_passable(cell) {
+                    total += 1;
+                    if self.is_seen((x, y)) {
+                        seen += 1;
+                    }
+                }
+            }
+        }
+
+        if total == 0 {
+            return 100.0;
+        }
+        seen as f32 * 100.0 / total as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze(rows: &[&str]) -> Maze {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn mark_ignores_cells_outside() {
+        let maze = maze(&["+---+", "|  g|", "+---+"]);
+        let mut exploration = Exploration::new(&maze);
+        for cell in [(-1, 1), (1, -1), (-3, -3), (5, 1), (1, 3), (i32::MAX, 0)] {
+            exploration.mark(cell);
+        }
+        assert_eq!(exploration.percent(&maze), 0.0);
+
+        exploration.mark((1, 1));
+        assert!(exploration.is_seen((1, 1)));
+        assert!(!exploration.is_seen((2, 1)));
+        assert!(!exploration.is_seen((99, 99)));
+    }
+
+    #[test]
+    fn percent_counts_only_passable_cells() {
+        //Tres celdas transitables: dos pasillos y la meta; las paredes no cuentan
+        let maze = maze(&["+---+", "|  g|", "+---+"]);
+        let mut exploration = Exploration::new(&maze);
+        exploration.mark((0, 0));
+        exploration.mark((1, 1));
+        assert!((exploration.percent(&maze) - 100.0 / 3.0).abs() < 0.001);
+
+        exploration.mark((2, 1));
+        exploration.mark((3, 1));
+        assert_eq!(exploration.percent(&maze), 100.0);
+    }
+
+    #[test]
+    fn no_passable_cells_is_fully_explored() {
+        let maze = maze(&["+-+", "|-|", "+-+"]);
+        assert_eq!(Exploration::new(&maze).percent(&maze), 100.0);
+        //Sin cuadrícula tampoco se divide por cero
+        assert_eq!(Exploration::default().percent(&Vec::new()), 100.0);
+    }
+}
@@ -0,0 +1,97 @@
+use raylib::prelude::*;
+
+//Fracción de niebla a partir de la cual un color ya no se distingue del de la niebla
+const OPAQUE: f32 = 254.0 / 255.0;
+
+//Niebla por distancia de la vista 3D: paredes, piso, techo y sprites se mezclan con su
+//color a medida que se alejan. Se configura por nivel con "fog = r g b densidad"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub color: Color,
+    pub density: f32, //Por celda; con 0.1 a 10 celdas queda un 37% del color original
+}
+
+impl Fog {
+    pub fn parse(value: &str) -> Result<Fog, String> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() != 4 {
+            return Err("fog needs '<r> <g> <b> <density>'".to_string());
+        }
+        let channel = |value: &str| value.parse::<u8>().map_err(|_| format!("invalid color channel '{}' (0-255)", value));
+        let density = parts[3].parse::<f32>().map_err(|_| format!("invalid fog density '{}'", parts[3]))?;
+        if density.is_nan() || density < 0.0 {
+            return Err(format!("fog density can't be negative, got '{}'", parts[3]));
+        }
+
+        Ok(Fog {
+            color: Color::new(channel(parts[0])?, channel(parts[1])?, channel(parts[2])?, 255),
+            density,
+        })
+    }
+
+    //Cuánta niebla hay entre la cámara y algo a esa distancia en celdas (0..1)
+    pub fn amount(&self, distance: f32) -> f32 {
+        1.0 - (-self.density * distance.max(0.0)).exp()
+    }
+
+    //Distancia en celdas desde la que todo es color de niebla; los rayos no siguen más allá
+    pub fn far_plane(&self) -> f32 {
+        if self.density <= 0.0 {
+            return f32::INFINITY;
+        }
+        -(1.0 - OPAQUE).ln() / self.density
+    }
+
+    pub fn apply(&self, color: Color, distance: f32) -> Color {
+        self.mix(color, self.amount(distance))
+    }
+
+    //Mezcla con una cantidad ya calculada, para cuando es la misma en toda una fila o un sprite
+    pub fn mix(&self, color: Color, amount: f32) -> Color {
+        let mix = |near: u8, far: u8| (near as f32 + (far as f32 - near as f32) * amount) as u8;
+        Color::new(mix(color.r, self.color.r), mix(color.g, self.color.g), mix(color.b, self.color.b), color.a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_color_and_density() {
+        let fog = Fog::parse("150 170 150 0.12").unwrap();
+        assert_eq!(fog.color, Color::new(150, 170, 150, 255));
+        assert_eq!(fog.density, 0.12);
+    }
+
+    #[test]
+    fn parse_rejects_bad_density() {
+        for density in ["-0.1", "NaN", "nan", "-inf", "abc"] {
+            let error = Fog::parse(&format!("150 170 150 {}", density)).unwrap_err();
+            //El mensaje se dibuja con la fuente de mapa de bits
+            assert!(error.is_ascii(), "{}", error);
+        }
+        assert!(Fog::parse("150 170 0.1").is_err());
+        assert!(Fog::parse("300 170 150 0.1").is_err());
+    }
+
+    #[test]
+    fn no_density_has_no_far_plane() {
+        let fog = Fog::parse("0 0 0 0").unwrap();
+        assert_eq!(fog.far_plane(), f32::INFINITY);
+        assert_eq!(fog.amount(1000.0), 0.0);
+        assert_eq!(fog.apply(Color::WHITE, 1000.0), Color::WHITE);
+    }
+
+    #[test]
+    fn far_plane_is_opaque() {
+        let fog = Fog { color: Color::new(100, 100, 100, 255), density: 0.2 };
+        let far = fog.far_plane();
+        assert!(far.is_finite() && far > 0.0);
+        assert!(fog.amount(far) >= OPAQUE - 0.0001);
+        //Cerca de la cámara no hay niebla y el alfa nunca cambia
+        assert_eq!(fog.amount(-5.0), 0.0);
+        assert_eq!(fog.apply(Color::new(10, 20, 30, 128), 0.0), Color::new(10, 20, 30, 128));
+        assert_eq!(fog.apply(Color::new(10, 20, 30, 128), far * 10.0).a, 128);
+    }
+}
@@ -1,39 +1,88 @@
 use raylib::prelude::*;
+use crate::textures::TextureRegistry;
+use crate::fog::Fog;
 
 pub struct Framebuffer {
     pub width: u32,
     pub height: u32,
-    pub color_buffer: Image,
+    //Píxeles RGBA de 8 bits por canal en orden de filas; se escriben directamente
+    //sin pasar por raylib, así el renderizado también funciona sin ventana
+    pub color_buffer: Vec<u8>,
     background_color: Color,
     current_color: Color,
-    //Cache de texturas para acceso rápido
-    pub wall_texture_cache: Option<Vec<Vec<Color>>>,
-    pub goal_texture_cache: Option<Vec<Vec<Color>>>, 
-    pub floor_texture_cache: Option<Vec<Vec<Color>>>,
+    //Registro de texturas por carácter del laberinto, piso y techo
+    pub textures: TextureRegistry,
+    //Niebla por distancia del nivel en curso para la vista 3D
+    pub fog: Option<Fog>,
+    //Profundidad perpendicular de la pared dibujada en cada columna
+    pub z_buffer: Vec<f32>,
+    //Textura de la GPU que se reutiliza en cada frame para presentar el buffer
+    texture: Option<Texture2D>,
 }
 
 impl Framebuffer {
     pub fn new(width: u32, height: u32) -> Self {
-        let color_buffer = Image::gen_image_color(width as i32, height as i32, Color::BLACK);
-        Framebuffer {
+        let mut framebuffer = Framebuffer {
             width,
             height,
-            color_buffer,
+            color_buffer: vec![0; width as usize * height as usize * 4],
             background_color: Color::BLACK,
             current_color: Color::WHITE,
-            wall_texture_cache: None,
-            goal_texture_cache: None, 
-            floor_texture_cache: None,
+            textures: TextureRegistry::new(),
+            fog: None,
+            z_buffer: vec![f32::INFINITY; width as usize],
+            texture: None,
+        };
+        framebuffer.clear();
+        framebuffer
+    }
+
+    //Cambia la resolución; la textura de la GPU se vuelve a crear en el próximo swap_buffers
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == self.width && height == self.height {
+            return;
         }
+        self.width = width;
+        self.height = height;
+        self.color_buffer = vec![0; width as usize * height as usize * 4];
+        self.z_buffer = vec![f32::INFINITY; width as usize];
+        self.clear();
     }
 
     pub fn clear(&mut self) {
-        self.color_buffer = Image::gen_image_color(self.width as i32, self.height as i32, self.background_color);
+        let color = [self.background_color.r, self.background_color.g, self.background_color.b, self.background_color.a];
+        for pixel in self.color_buffer.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
     }
 
     pub fn set_pixel(&mut self, x: u32, y: u32) {
+        self.set_pixel_color(x, y, self.current_color);
+    }
+
+    pub fn set_pixel_color(&mut self, x: u32, y: u32, color: Color) {
+        if x < self.width && y < self.height {
+            let index = (y as usize * self.width as usize + x as usize) * 4;
+            self.color_buffer[index..index + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+    }
+
+    //Mezcla el color con lo que ya hay según su alfa (sprites translúcidos)
+    pub fn blend_pixel(&mut self, x: u32, y: u32, color: Color) {
+        if let Some(under) = self.get_pixel(x, y) {
+            let alpha = color.a as f32 / 255.0;
+            let mix = |top: u8, bottom: u8| (top as f32 * alpha + bottom as f32 * (1.0 - alpha)) as u8;
+            self.set_pixel_color(x, y, Color::new(mix(color.r, under.r), mix(color.g, under.g), mix(color.b, under.b), 255));
+        }
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<Color> {
         if x < self.width && y < self.height {
-            self.color_buffer.draw_pixel(x as i32, y as i32, self.current_color);
+            let index = (y as usize * self.width as usize + x as usize) * 4;
+            let pixel = &self.color_buffer[index..index + 4];
+            Some(Color::new(pixel[0], pixel[1], pixel[2], pixel[3]))
+        } else {
+            None
         }
     }
 
@@ -45,87 +94,29 @@ impl Framebuffer {
         self.current_color = color;
     }
     
-    pub fn load_texture_cache(&mut self, texture: &Image) {
-        //Crear cache de la textura de pared para acceso rápido
-        let mut cache = Vec::new();
-        let mut temp_texture = texture.clone();
-        
-        for y in 0..texture.height {
-            let mut row = Vec::new();
-            for x in 0..texture.width {
-                let color = temp_texture.get_color(x, y);
-                row.push(color);
-            }
-            cache.push(row);
-        }
-        self.wall_texture_cache = Some(cache);
-    }
-    
-    pub fn load_goal_texture_cache(&mut self, texture: &Image) {
-        //Crear cache de la textura de meta para acceso rápido
-        let mut cache = Vec::new();
-        let mut temp_texture = texture.clone();
-        
-        for y in 0..texture.height {
-            let mut row = Vec::new();
-            for x in 0..texture.width {
-                let color = temp_texture.get_color(x, y);
-                row.push(color);
-            }
-            cache.push(row);
-        }
-        self.goal_texture_cache = Some(cache);
-    }
-    
-    pub fn load_floor_texture_cache(&mut self, texture: &Image) {
-        //Crear cache de la textura de piso para acceso rápido
-        let mut cache = Vec::new();
-        let mut temp_texture = texture.clone();
-        
-        for y in 0..texture.height {
-            let mut row = Vec::new();
-            for x in 0..texture.width {
-                let color = temp_texture.get_color(x, y);
-                row.push(color);
-            }
-            cache.push(row);
-        }
-        self.floor_texture_cache = Some(cache);
+    pub fn get_wall_texture_pixel(&self, cell: char, tx: f32, ty: f32) -> Option<Color> {
+        self.textures.wall_pixel(cell, tx, ty)
     }
     
-    pub fn get_texture_pixel(&self, tx: f32, ty: f32) -> Color {
-        if let Some(ref cache) = self.wall_texture_cache {
-            let tex_x = ((tx * cache[0].len() as f32) as usize).min(cache[0].len() - 1);
-            let tex_y = ((ty * cache.len() as f32) as usize).min(cache.len() - 1);
-            cache[tex_y][tex_x]
-        } else {
-            //Fallback a textura procedural si no hay cache
-            let r = (tx * 255.0) as u8;
-            let g = (ty * 255.0) as u8;
-            let b = ((tx + ty) * 127.5) as u8;
-            Color::new(r.max(100), g.max(80), b.max(60), 255)
-        }
-    }
-    
-    pub fn get_goal_texture_pixel(&self, tx: f32, ty: f32) -> Color {
-        if let Some(ref cache) = self.goal_texture_cache {
-            let tex_x = ((tx * cache[0].len() as f32) as usize).min(cache[0].len() - 1);
-            let tex_y = ((ty * cache.len() as f32) as usize).min(cache.len() - 1);
-            cache[tex_y][tex_x]
+    pub fn get_floor_texture_pixel(&self, tx: f32, ty: f32) -> Color {
+        if let Some(color) = self.textures.floor_pixel(tx, ty) {
+            color
         } else {
-            //Fallback a color verde si no hay cache
-            Color::GREEN
+            //Fallback a baldosas procedurales si no hay textura
+            if (tx < 0.5) == (ty < 0.5) {
+                Color::new(192, 201, 135, 255)
+            } else {
+                Color::new(172, 181, 115, 255)
+            }
         }
     }
     
-    pub fn get_floor_texture_pixel(&self, tx: f32, ty: f32) -> Color {
-        if let Some(ref cache) = self.floor_texture_cache {
-            let tex_x = ((tx * cache[0].len() as f32) as usize).min(cache[0].len() - 1);
-            let tex_y = ((ty * cache.len() as f32) as usize).min(cache.len() - 1);
-            cache[tex_y][tex_x]
+    pub fn get_ceiling_texture_pixel(&self, tx: f32, ty: f32) -> Color {
+        if let Some(color) = self.textures.ceiling_pixel(tx, ty) {
+            color
         } else {
-            //Fallback a color sólido si no hay cache
-            Color::new(80, 60, 40, 255)
+            //Fallback al color de fondo si no hay textura
+            self.background_color
         }
     }
 
@@ -703,6 +694,186 @@ impl Framebuffer {
                 0b01000,
                 0b10000,
             ],
+            'w' => [
+                0b00000,
+                0b10001,
+                0b10001,
+                0b10101,
+                0b10101,
+                0b10101,
+                0b01010,
+            ],
+            'H' => [
+                0b10001,
+                0b10001,
+                0b10001,
+                0b11111,
+                0b10001,
+                0b10001,
+                0b10001,
+            ],
+            'K' => [
+                0b10001,
+                0b10010,
+                0b10100,
+                0b11000,
+                0b10100,
+                0b10010,
+                0b10001,
+            ],
+            'Q' => [
+                0b01110,
+                0b10001,
+                0b10001,
+                0b10001,
+                0b10101,
+                0b10010,
+                0b01101,
+            ],
+            'X' => [
+                0b10001,
+                0b10001,
+                0b01010,
+                0b00100,
+                0b01010,
+                0b10001,
+                0b10001,
+            ],
+            'Z' => [
+                0b11111,
+                0b00001,
+                0b00010,
+                0b00100,
+                0b01000,
+                0b10000,
+                0b11111,
+            ],
+            '_' => [
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00000,
+                0b11111,
+            ],
+            '\'' => [
+                0b00100,
+                0b00100,
+                0b01000,
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00000,
+            ],
+            '-' => [
+                0b00000,
+                0b00000,
+                0b00000,
+                0b11111,
+                0b00000,
+                0b00000,
+                0b00000,
+            ],
+            '=' => [
+                0b00000,
+                0b00000,
+                0b11111,
+                0b00000,
+                0b11111,
+                0b00000,
+                0b00000,
+            ],
+            '<' => [
+                0b00010,
+                0b00100,
+                0b01000,
+                0b10000,
+                0b01000,
+                0b00100,
+                0b00010,
+            ],
+            '>' => [
+                0b01000,
+                0b00100,
+                0b00010,
+                0b00001,
+                0b00010,
+                0b00100,
+                0b01000,
+            ],
+            ',' => [
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00110,
+                0b00100,
+                0b01000,
+            ],
+            '+' => [
+                0b00000,
+                0b00100,
+                0b00100,
+                0b11111,
+                0b00100,
+                0b00100,
+                0b00000,
+            ],
+            '|' => [
+                0b00100,
+                0b00100,
+                0b00100,
+                0b00100,
+                0b00100,
+                0b00100,
+                0b00100,
+            ],
+            '%' => [
+                0b11001,
+                0b11010,
+                0b00010,
+                0b00100,
+                0b01000,
+                0b01011,
+                0b10011,
+            ],
+            '?' => [
+                0b01110,
+                0b10001,
+                0b00001,
+                0b00110,
+                0b00100,
+                0b00000,
+                0b00100,
+            ],
+            '[' => [
+                0b01110,
+                0b01000,
+                0b01000,
+                0b01000,
+                0b01000,
+                0b01000,
+                0b01110,
+            ],
+            ']' => [
+                0b01110,
+                0b00010,
+                0b00010,
+                0b00010,
+                0b00010,
+                0b00010,
+                0b01110,
+            ],
+            '"' => [
+                0b01010,
+                0b01010,
+                0b01010,
+                0b00000,
+                0b00000,
+                0b00000,
+                0b00000,
+            ],
             ' ' => [
                 0b00000,
                 0b00000,
@@ -747,18 +918,87 @@ impl Framebuffer {
         }
     }
 
-    pub fn _render_to_file(&self, file_path: &str) {
-        self.color_buffer.export_image(file_path);
+    //Guarda el frame actual como PNG; no necesita ventana
+    pub fn render_to_file(&self, file_path: &str) -> Result<(), String> {
+        image::save_buffer(file_path, &self.color_buffer, self.width, self.height, image::ColorType::Rgba8)
+            .map_err(|e| format!("no se pudo guardar el frame en {}: {}", file_path, e))
     }
 
     pub fn swap_buffers(
-        &self,
+        &mut self,
         window: &mut RaylibHandle,
         raylib_thread: &RaylibThread,
     ) {
-        if let Ok(texture) = window.load_texture_from_image(raylib_thread, &self.color_buffer) {
+        //Crear la textura solo la primera vez o si cambió el tamaño del buffer
+        let needs_texture = match self.texture {
+            Some(ref texture) => texture.width != self.width as i32 || texture.height != self.height as i32,
+            None => true,
+        };
+        if needs_texture {
+            let image = Image::gen_image_color(self.width as i32, self.height as i32, Color::BLACK);
+            self.texture = window.load_texture_from_image(raylib_thread, &image).ok();
+        }
+
+        if let Some(ref mut texture) = self.texture {
+            if let Err(e) = texture.update_texture(&self.color_buffer) {
+                println!("No se pudo actualizar la textura del frame: {}", e);
+            }
             let mut renderer = window.begin_drawing(raylib_thread);
-            renderer.draw_texture(&texture, 0, 0, Color::WHITE);
+            renderer.draw_texture(&*texture, 0, 0, Color::WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get_pixel() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        let color = Color::new(10, 20, 30, 40);
+        framebuffer.set_pixel_color(3, 2, color);
+
+        assert_eq!(framebuffer.get_pixel(3, 2), Some(color));
+        //Orden de filas, RGBA
+        assert_eq!(&framebuffer.color_buffer[(2 * 4 + 3) * 4..][..4], &[10, 20, 30, 40]);
+        //Fuera del buffer no se escribe ni se lee
+        framebuffer.set_pixel_color(4, 0, color);
+        framebuffer.set_pixel_color(0, 3, color);
+        assert_eq!(framebuffer.get_pixel(4, 0), None);
+        assert_eq!(framebuffer.get_pixel(0, 3), None);
+
+        framebuffer.set_current_color(Color::RED);
+        framebuffer.set_pixel(0, 0);
+        assert_eq!(framebuffer.get_pixel(0, 0), Some(Color::RED));
+    }
+
+    #[test]
+    fn clear_uses_background_color() {
+        let mut framebuffer = Framebuffer::new(5, 5);
+        framebuffer.set_pixel_color(2, 2, Color::RED);
+        framebuffer.set_background_color(Color::new(1, 2, 3, 255));
+        framebuffer.clear();
+
+        for y in 0..5 {
+            for x in 0..5 {
+                assert_eq!(framebuffer.get_pixel(x, y), Some(Color::new(1, 2, 3, 255)));
+            }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn resize_reallocates_buffers() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.set_background_color(Color::BLUE);
+        framebuffer.z_buffer[0] = 5.0;
+        framebuffer.resize(10, 6);
+
+        assert_eq!((framebuffer.width, framebuffer.height), (10, 6));
+        assert_eq!(framebuffer.color_buffer.len(), 10 * 6 * 4);
+        assert_eq!(framebuffer.z_buffer.len(), 10);
+        assert!(framebuffer.z_buffer.iter().all(|&depth| depth == f32::INFINITY));
+        assert_eq!(framebuffer.get_pixel(9, 5), Some(Color::BLUE));
+        assert_eq!(framebuffer.get_pixel(10, 0), None);
+    }
+}
@@ -1,10 +1,20 @@
 use raylib::prelude::*;
+use crate::generator::{GeneratorOptions, parse_chance};
+use crate::stats::{RunStats, Record, INITIALS_LENGTH};
+use crate::generator::Algorithm;
+use crate::replay::{Replay, ReplayPlayer, new_seed};
+use crate::ghost::Ghost;
+use crate::objectives::FailCondition;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum GameState {
     Welcome,
     Playing,
     Victory,
+    Defeat,   //Se cumplió una condición de derrota del nivel
+    Controls, //Pantalla para reasignar controles
+    Paused,   //Partida en pausa con el menú superpuesto
+    Settings, //Ajustes de volumen, FOV, sensibilidad y resolución
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -12,14 +22,17 @@ pub enum Difficulty {
     Easy,
     Medium,
     Difficult,
+    Random(GeneratorOptions), //Laberinto generado a partir de una semilla
 }
 
 impl Difficulty {
-    pub fn get_maze_file(&self) -> &str {
+    //Los niveles aleatorios no tienen archivo, se generan con su semilla
+    pub fn get_maze_file(&self) -> Option<&str> {
         match self {
-            Difficulty::Easy => "maze_easy.txt",
-            Difficulty::Medium => "maze_medium.txt", 
-            Difficulty::Difficult => "maze_difficult.txt",
+            Difficulty::Easy => Some("maze_easy.txt"),
+            Difficulty::Medium => Some("maze_medium.txt"), 
+            Difficulty::Difficult => Some("maze_difficult.txt"),
+            Difficulty::Random(_) => None,
         }
     }
     
@@ -28,14 +41,69 @@ impl Difficulty {
             Difficulty::Easy => "EASY",
             Difficulty::Medium => "MEDIUM",
             Difficulty::Difficult => "DIFFICULT",
+            Difficulty::Random(_) => "RANDOM",
         }
     }
+    
+    //Clave de la tabla de récords; un laberinto aleatorio depende de todas sus opciones
+    pub fn record_key(&self) -> String {
+        match self {
+            Difficulty::Random(options) => format!(
+                "random-{}-{}x{}-{}-{}-{}",
+                options.seed, options.width, options.height, options.algorithm.get_name(), options.loops, options.braid,
+            ),
+            _ => self.get_name().to_lowercase(),
+        }
+    }
+    
+    //Inverso de record_key, para saber qué nivel corresponde a una repetición
+    pub fn from_record_key(key: &str) -> Option<Difficulty> {
+        match key {
+            "easy" => return Some(Difficulty::Easy),
+            "medium" => return Some(Difficulty::Medium),
+            "difficult" => return Some(Difficulty::Difficult),
+            _ => {},
+        }
+        
+        let parts: Vec<&str> = key.strip_prefix("random-")?.split('-').collect();
+        if parts.len() != 5 {
+            return None;
+        }
+        let (width, height) = parts[1].split_once('x')?;
+        Some(Difficulty::Random(GeneratorOptions {
+            seed: parts[0].parse().ok()?,
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+            algorithm: Algorithm::from_name(parts[2])?,
+            loops: parse_chance(parts[3])?,
+            braid: parse_chance(parts[4])?,
+        }))
+    }
 }
 
 pub struct GameManager {
     pub state: GameState,
     pub current_difficulty: Difficulty,
     pub selected_option: usize, //Para navegación en el menú
+    pub load_error: Option<String>, //Último error al cargar un nivel, se muestra en el menú
+    pub random_options: GeneratorOptions, //Parámetros para la dificultad RANDOM
+    pub fixed_seed: bool, //Si la semilla vino de la línea de comandos no se cambia
+    pub controls_selected: usize, //Fila seleccionada en la pantalla de controles
+    pub capturing_binding: bool, //Esperando el input para la acción seleccionada
+    pub settings_selected: usize, //Fila seleccionada en la pantalla de ajustes
+    pub settings_return: GameState, //Pantalla a la que se vuelve al salir de los ajustes
+    pub run: RunStats, //Tiempo, distancia y reinicios de la partida en curso
+    pub initials: [char; INITIALS_LENGTH], //Iniciales para la tabla de récords
+    pub initials_cursor: usize,
+    pub entering_initials: bool, //Pantalla de victoria pidiendo las iniciales
+    pub last_record: Option<Record>, //Récord de la última victoria, ya guardado
+    pub previous_best: Option<f32>, //Mejor tiempo personal antes de la última victoria
+    pub run_seed: u64, //Semilla de fastrand de la partida en curso
+    pub recording: Replay, //Input de la partida en curso, paso por paso
+    pub playback: Option<ReplayPlayer>, //Repetición que se está reproduciendo en lugar del input
+    pub ghost: Option<Ghost>, //Trayectoria de la mejor partida del nivel actual
+    pub ghost_recording: Ghost, //Trayectoria de la partida en curso
+    pub defeat: Option<FailCondition>, //Por qué se perdió la última partida
 }
 
 impl GameManager {
@@ -44,6 +112,25 @@ impl GameManager {
             state: GameState::Welcome,
             current_difficulty: Difficulty::Easy,
             selected_option: 0,
+            load_error: None,
+            random_options: GeneratorOptions::default(),
+            fixed_seed: false,
+            controls_selected: 0,
+            capturing_binding: false,
+            settings_selected: 0,
+            settings_return: GameState::Welcome,
+            run: RunStats::default(),
+            initials: ['A'; INITIALS_LENGTH],
+            initials_cursor: 0,
+            entering_initials: false,
+            last_record: None,
+            previous_best: None,
+            run_seed: 0,
+            recording: Replay::new("", 0),
+            playback: None,
+            ghost: None,
+            ghost_recording: Ghost::default(),
+            defeat: None,
         }
     }
     
@@ -52,12 +139,124 @@ impl GameManager {
         self.selected_option = 0;
     }
     
+    pub fn open_controls(&mut self) {
+        self.state = GameState::Controls;
+        self.controls_selected = 0;
+        self.capturing_binding = false;
+    }
+    
+    //Los ajustes se abren desde el menú principal o desde la pausa
+    pub fn open_settings(&mut self) {
+        self.settings_return = self.state.clone();
+        self.state = GameState::Settings;
+        self.settings_selected = 0;
+    }
+    
+    pub fn pause(&mut self) {
+        self.state = GameState::Paused;
+        self.selected_option = 0;
+    }
+    
+    pub fn resume(&mut self) {
+        self.state = GameState::Playing;
+    }
+    
+    //Dificultad aleatoria con una semilla nueva (o la fija si se indicó una)
+    pub fn random_difficulty(&mut self) -> Difficulty {
+        if !self.fixed_seed {
+            self.random_options.seed = fastrand::u64(0..1_000_000);
+        }
+        Difficulty::Random(self.random_options.clone())
+    }
+    
     pub fn start_game(&mut self, difficulty: Difficulty) {
         self.current_difficulty = difficulty;
+        self.load_error = None;
+        self.state = GameState::Playing;
+        self.playback = None;
+        self.new_run();
+    }
+    
+    //Reproducir una partida grabada en su mismo nivel
+    pub fn start_replay(&mut self, replay: Replay) -> Result<(), String> {
+        let difficulty = Difficulty::from_record_key(&replay.level)
+            .ok_or(format!("unknown level '{}' in replay", replay.level))?;
+        self.current_difficulty = difficulty;
+        self.load_error = None;
+        self.state = GameState::Playing;
+        self.playback = Some(ReplayPlayer::new(replay));
+        self.new_run();
+        Ok(())
+    }
+    
+    //Jugar otra vez el mismo nivel desde cero (o volver a ver la repetición)
+    pub fn restart_run(&mut self) {
         self.state = GameState::Playing;
+        if let Some(ref mut playback) = self.playback {
+            playback.rewind();
+        }
+        self.new_run();
+    }
+    
+    //Estadísticas, semilla y grabación nuevas; una repetición usa su semilla original
+    fn new_run(&mut self) {
+        self.run = RunStats::default();
+        self.run_seed = match self.playback {
+            Some(ref playback) => playback.replay.seed,
+            None => new_seed(),
+        };
+        self.recording = Replay::new(&self.current_difficulty.record_key(), self.run_seed);
+        self.ghost_recording = Ghost::default();
     }
     
     pub fn win_game(&mut self) {
         self.state = GameState::Victory;
+        self.entering_initials = self.playback.is_none();
+        self.initials_cursor = 0;
+        self.last_record = None;
+    }
+    
+    pub fn lose_game(&mut self, reason: FailCondition) {
+        self.state = GameState::Defeat;
+        self.defeat = Some(reason);
+        self.selected_option = 0;
+    }
+    
+    pub fn set_initials(&mut self, initials: &str) {
+        for (slot, ch) in self.initials.iter_mut().zip(initials.chars()) {
+            *slot = ch;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_key_round_trip() {
+        for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Difficult] {
+            assert_eq!(Difficulty::from_record_key(&difficulty.record_key()), Some(difficulty));
+        }
+
+        for algorithm in [Algorithm::RecursiveBacktracker, Algorithm::Prim, Algorithm::Kruskal, Algorithm::Eller] {
+            let difficulty = Difficulty::Random(GeneratorOptions {
+                seed: u64::MAX,
+                width: 7,
+                height: 31,
+                algorithm,
+                loops: 0.1,
+                braid: 1.0 / 3.0,
+            });
+            let key = difficulty.record_key();
+            assert_eq!(Difficulty::from_record_key(&key), Some(difficulty), "{}", key);
+        }
+    }
+
+    #[test]
+    fn record_key_rejects_out_of_range_chances() {
+        assert_eq!(Difficulty::from_record_key("random-1-10x10-prim--0.5-0"), None);
+        assert_eq!(Difficulty::from_record_key("random-1-10x10-prim-0-1.5"), None);
+        assert_eq!(Difficulty::from_record_key("random-1-10x10-prim-0"), None);
     }
 }
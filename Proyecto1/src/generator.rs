@@ -0,0 +1,469 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use crate::level::Level;
+use crate::maze::Maze;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    RecursiveBacktracker,
+    Prim,
+    Kruskal,
+    Eller,
+}
+
+impl Algorithm {
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name.to_lowercase().as_str() {
+            "backtracker" | "recursive-backtracker" | "dfs" => Some(Algorithm::RecursiveBacktracker),
+            "prim" | "prims" => Some(Algorithm::Prim),
+            "kruskal" | "kruskals" => Some(Algorithm::Kruskal),
+            "eller" | "ellers" => Some(Algorithm::Eller),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            Algorithm::RecursiveBacktracker => "backtracker",
+            Algorithm::Prim => "prim",
+            Algorithm::Kruskal => "kruskal",
+            Algorithm::Eller => "eller",
+        }
+    }
+}
+
+//Parámetros del generador; la misma combinación siempre produce el mismo laberinto
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorOptions {
+    pub seed: u64,
+    pub width: usize,  //Ancho en celdas lógicas (cada celda ocupa 3x2 caracteres)
+    pub height: usize, //Alto en celdas lógicas
+    pub algorithm: Algorithm,
+    pub loops: f32, //Probabilidad de abrir cada pared interior extra (crea ciclos)
+    pub braid: f32, //Probabilidad de eliminar cada callejón sin salida
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            seed: 0,
+            width: 12,
+            height: 10,
+            algorithm: Algorithm::RecursiveBacktracker,
+            loops: 0.0,
+            braid: 0.0,
+        }
+    }
+}
+
+//Probabilidad de --loops y --braid, entre 0 y 1
+pub fn parse_chance(value: &str) -> Option<f32> {
+    //abs convierte "-0" en 0
+    value.parse::<f32>().ok().filter(|chance| (0.0..=1.0).contains(chance)).map(f32::abs)
+}
+
+//Cuadrícula de celdas lógicas con los pasajes abiertos hacia el este y el sur
+struct Grid {
+    width: usize,
+    height: usize,
+    east: Vec<bool>,
+    south: Vec<bool>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            east: vec![false; width * height],
+            south: vec![false; width * height],
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    //Abrir el pasaje entre dos celdas vecinas
+    fn carve(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let (first, second) = if (a.1, a.0) < (b.1, b.0) { (a, b) } else { (b, a) };
+        let index = self.index(first.0, first.1);
+        if second.0 > first.0 {
+            self.east[index] = true;
+        } else if second.1 > first.1 {
+            self.south[index] = true;
+        }
+    }
+
+    fn is_open(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let (first, second) = if (a.1, a.0) < (b.1, b.0) { (a, b) } else { (b, a) };
+        let index = self.index(first.0, first.1);
+        if second.0 > first.0 {
+            self.east[index]
+        } else {
+            self.south[index]
+        }
+    }
+
+    fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut result = Vec::with_capacity(4);
+        if y > 0 {
+            result.push((x, y - 1));
+        }
+        if x + 1 < self.width {
+            result.push((x + 1, y));
+        }
+        if y + 1 < self.height {
+            result.push((x, y + 1));
+        }
+        if x > 0 {
+            result.push((x - 1, y));
+        }
+        result
+    }
+
+    fn open_neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        self.neighbors(x, y)
+            .into_iter()
+            .filter(|&n| self.is_open((x, y), n))
+            .collect()
+    }
+}
+
+pub fn generate_maze(options: &GeneratorOptions) -> Maze {
+    let width = options.width.max(2);
+    let height = options.height.max(2);
+    let mut rng = fastrand::Rng::with_seed(options.seed);
+    let mut grid = Grid::new(width, height);
+
+    match options.algorithm {
+        Algorithm::RecursiveBacktracker => recursive_backtracker(&mut grid, &mut rng),
+        Algorithm::Prim => prim(&mut grid, &mut rng),
+        Algorithm::Kruskal => kruskal(&mut grid, &mut rng),
+        Algorithm::Eller => eller(&mut grid, &mut rng),
+    }
+
+    if options.loops > 0.0 {
+        add_loops(&mut grid, &mut rng, options.loops);
+    }
+    if options.braid > 0.0 {
+        braid(&mut grid, &mut rng, options.braid);
+    }
+
+    let goal = farthest_cell(&grid, (0, 0));
+    to_maze(&grid, goal)
+}
+
+//Nivel listo para jugar: spawn en la primera celda mirando hacia el pasillo abierto
+pub fn generate_level(options: &GeneratorOptions) -> Level {
+    let maze = generate_maze(options);
+    let name = format!("Random {} ({})", options.seed, options.algorithm.get_name());
+    let mut level = Level::from_maze(&name, maze);
+    level.spawn_cell = (1, 1);
+    level.spawn_angle = if level.maze[1][3] == ' ' { 0.0 } else { PI / 2.0 };
+    level
+}
+
+fn recursive_backtracker(grid: &mut Grid, rng: &mut fastrand::Rng) {
+    let mut visited = vec![false; grid.width * grid.height];
+    let start = (rng.usize(0..grid.width), rng.usize(0..grid.height));
+    let mut stack = vec![start];
+    visited[grid.index(start.0, start.1)] = true;
+
+    while let Some(&(x, y)) = stack.last() {
+        let unvisited: Vec<(usize, usize)> = grid.neighbors(x, y)
+            .into_iter()
+            .filter(|&(nx, ny)| !visited[grid.index(nx, ny)])
+            .collect();
+
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let next = unvisited[rng.usize(0..unvisited.len())];
+        grid.carve((x, y), next);
+        visited[grid.index(next.0, next.1)] = true;
+        stack.push(next);
+    }
+}
+
+fn prim(grid: &mut Grid, rng: &mut fastrand::Rng) {
+    let mut in_maze = vec![false; grid.width * grid.height];
+    let start = (rng.usize(0..grid.width), rng.usize(0..grid.height));
+    in_maze[grid.index(start.0, start.1)] = true;
+
+    //Paredes frontera: (celda dentro del laberinto, vecina)
+    let mut frontier: Vec<((usize, usize), (usize, usize))> = grid.neighbors(start.0, start.1)
+        .into_iter()
+        .map(|n| (start, n))
+        .collect();
+
+    while !frontier.is_empty() {
+        let (from, to) = frontier.swap_remove(rng.usize(0..frontier.len()));
+        let to_index = grid.index(to.0, to.1);
+        if in_maze[to_index] {
+            continue;
+        }
+
+        grid.carve(from, to);
+        in_maze[to_index] = true;
+        for n in grid.neighbors(to.0, to.1) {
+            if !in_maze[grid.index(n.0, n.1)] {
+                frontier.push((to, n));
+            }
+        }
+    }
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    //Compresión de caminos
+    let mut current = i;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+fn kruskal(grid: &mut Grid, rng: &mut fastrand::Rng) {
+    let mut edges = Vec::new();
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if x + 1 < grid.width {
+                edges.push(((x, y), (x + 1, y)));
+            }
+            if y + 1 < grid.height {
+                edges.push(((x, y), (x, y + 1)));
+            }
+        }
+    }
+    rng.shuffle(&mut edges);
+
+    let mut parents: Vec<usize> = (0..grid.width * grid.height).collect();
+    for (a, b) in edges {
+        let root_a = find(&mut parents, grid.index(a.0, a.1));
+        let root_b = find(&mut parents, grid.index(b.0, b.1));
+        if root_a != root_b {
+            parents[root_b] = root_a;
+            grid.carve(a, b);
+        }
+    }
+}
+
+fn eller(grid: &mut Grid, rng: &mut fastrand::Rng) {
+    let width = grid.width;
+    let mut sets = vec![0usize; width];
+    let mut next_set = 1;
+
+    for y in 0..grid.height {
+        let last_row = y + 1 == grid.height;
+
+        //Celdas sin conjunto reciben uno nuevo
+        for set in sets.iter_mut() {
+            if *set == 0 {
+                *set = next_set;
+                next_set += 1;
+            }
+        }
+
+        //Unir vecinas horizontales de conjuntos distintos (todas en la última fila)
+        for x in 0..width - 1 {
+            if sets[x] != sets[x + 1] && (last_row || rng.bool()) {
+                grid.carve((x, y), (x + 1, y));
+                let (keep, replace) = (sets[x], sets[x + 1]);
+                for set in sets.iter_mut() {
+                    if *set == replace {
+                        *set = keep;
+                    }
+                }
+            }
+        }
+
+        if last_row {
+            break;
+        }
+
+        //Cada conjunto baja al menos una vez a la siguiente fila
+        let mut next_row = vec![0usize; width];
+        let mut distinct: Vec<usize> = sets.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        for set in distinct {
+            let mut members: Vec<usize> = (0..width).filter(|&x| sets[x] == set).collect();
+            rng.shuffle(&mut members);
+            let count = 1 + rng.usize(0..members.len());
+            for &x in &members[..count] {
+                grid.carve((x, y), (x, y + 1));
+                next_row[x] = set;
+            }
+        }
+        sets = next_row;
+    }
+}
+
+//Abrir paredes interiores al azar para crear ciclos
+fn add_loops(grid: &mut Grid, rng: &mut fastrand::Rng, chance: f32) {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            for n in [(x + 1, y), (x, y + 1)] {
+                if n.0 < grid.width && n.1 < grid.height && !grid.is_open((x, y), n) && rng.f32() < chance {
+                    grid.carve((x, y), n);
+                }
+            }
+        }
+    }
+}
+
+//Eliminar callejones sin salida, prefiriendo unir dos callejones entre sí
+fn braid(grid: &mut Grid, rng: &mut fastrand::Rng, chance: f32) {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if grid.open_neighbors(x, y).len() != 1 || rng.f32() >= chance {
+                continue;
+            }
+
+            let closed: Vec<(usize, usize)> = grid.neighbors(x, y)
+                .into_iter()
+                .filter(|&n| !grid.is_open((x, y), n))
+                .collect();
+            if closed.is_empty() {
+                continue;
+            }
+
+            let dead_ends: Vec<(usize, usize)> = closed.iter()
+                .copied()
+                .filter(|&(nx, ny)| grid.open_neighbors(nx, ny).len() == 1)
+                .collect();
+            let candidates = if dead_ends.is_empty() { closed } else { dead_ends };
+            let target = candidates[rng.usize(0..candidates.len())];
+            grid.carve((x, y), target);
+        }
+    }
+}
+
+//Celda más lejana (en pasos) desde el inicio; ahí va la meta
+fn farthest_cell(grid: &Grid, start: (usize, usize)) -> (usize, usize) {
+    let mut distance = vec![usize::MAX; grid.width * grid.height];
+    let mut queue = VecDeque::new();
+    let mut farthest = start;
+
+    distance[grid.index(start.0, start.1)] = 0;
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        let d = distance[grid.index(x, y)];
+        if d > distance[grid.index(farthest.0, farthest.1)] {
+            farthest = (x, y);
+        }
+        for n in grid.open_neighbors(x, y) {
+            let n_index = grid.index(n.0, n.1);
+            if distance[n_index] == usize::MAX {
+                distance[n_index] = d + 1;
+                queue.push_back(n);
+            }
+        }
+    }
+
+    farthest
+}
+
+//Convertir a caracteres: cada celda ocupa 3 columnas y 2 filas, como los mapas escritos a mano
+fn to_maze(grid: &Grid, goal: (usize, usize)) -> Maze {
+    let columns = grid.width * 3 + 1;
+    let rows = grid.height * 2 + 1;
+    let mut maze = vec![vec![' '; columns]; rows];
+
+    for row in (0..rows).step_by(2) {
+        for (col, cell) in maze[row].iter_mut().enumerate() {
+            *cell = if col % 3 == 0 { '+' } else { '-' };
+        }
+    }
+    for row in (1..rows).step_by(2) {
+        maze[row][0] = '|';
+        maze[row][columns - 1] = '|';
+    }
+
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let index = grid.index(x, y);
+            let row = y * 2 + 1;
+            let col = x * 3;
+
+            if x + 1 < grid.width && !grid.east[index] {
+                maze[row][col + 3] = '|';
+            }
+            if y + 1 < grid.height && grid.south[index] {
+                maze[row + 1][col + 1] = ' ';
+                maze[row + 1][col + 2] = ' ';
+            }
+        }
+    }
+
+    maze[goal.1 * 2 + 1][goal.0 * 3 + 2] = 'g';
+    maze
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::validate_level;
+
+    const ALGORITHMS: [Algorithm; 4] = [Algorithm::RecursiveBacktracker, Algorithm::Prim, Algorithm::Kruskal, Algorithm::Eller];
+
+    fn options(seed: u64, algorithm: Algorithm, loops: f32, braid: f32) -> GeneratorOptions {
+        GeneratorOptions { seed, width: 9, height: 7, algorithm, loops, braid }
+    }
+
+    #[test]
+    fn same_options_same_maze() {
+        for algorithm in ALGORITHMS {
+            let options = options(42, algorithm, 0.1, 0.3);
+            //El estado global de fastrand no influye, el generador usa su propio Rng
+            fastrand::seed(1);
+            let first = generate_maze(&options);
+            fastrand::seed(2);
+            let second = generate_maze(&options);
+            assert_eq!(first, second, "{}", algorithm.get_name());
+            assert_eq!((first.len(), first[0].len()), (2 * 7 + 1, 3 * 9 + 1));
+        }
+    }
+
+    #[test]
+    fn seed_changes_the_maze() {
+        for algorithm in ALGORITHMS {
+            assert_ne!(generate_maze(&options(1, algorithm, 0.0, 0.0)), generate_maze(&options(2, algorithm, 0.0, 0.0)), "{}", algorithm.get_name());
+        }
+    }
+
+    #[test]
+    fn goal_is_reachable() {
+        for algorithm in ALGORITHMS {
+            for seed in 0..20 {
+                for (loops, braid) in [(0.0, 0.0), (0.2, 0.0), (0.0, 1.0)] {
+                    let options = options(seed, algorithm, loops, braid);
+                    let mut level = generate_level(&options);
+                    //validate_level comprueba que haya meta y que se llegue desde el spawn
+                    if let Err(e) = validate_level(&mut level, 0) {
+                        panic!("{:?}: {}", options, e);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_chance_range() {
+        assert_eq!(parse_chance("0.25"), Some(0.25));
+        assert_eq!(parse_chance("1"), Some(1.0));
+        assert_eq!(parse_chance("-0").map(|chance| chance.is_sign_negative()), Some(false));
+        assert_eq!(parse_chance("-0.1"), None);
+        assert_eq!(parse_chance("1.5"), None);
+        assert_eq!(parse_chance("NaN"), None);
+    }
+}
@@ -0,0 +1,108 @@
+use raylib::prelude::*;
+use std::fs;
+use std::path::Path;
+use crate::player::Player;
+
+pub const GHOST_DIR: &str = "ghosts";
+
+//Trayectoria de una partida: posición y ángulo del jugador en cada paso de simulación.
+//La muestra i es la pose después de i pasos (la 0 es el spawn)
+#[derive(Debug, Clone, Default)]
+pub struct Ghost {
+    pub time: f32, //Tiempo con el que se llegó a la meta
+    samples: Vec<(Vector2, f32)>,
+}
+
+//Un fantasma por nivel; en los aleatorios la clave ya incluye la semilla
+pub fn ghost_path(level_key: &str) -> String {
+    format!("{}/{}.ghost", GHOST_DIR, level_key)
+}
+
+impl Ghost {
+    pub fn push(&mut self, player: &Player) {
+        self.samples.push((player.pos, player.a));
+    }
+
+    //Pose del fantasma con el mismo paso e interpolación que el jugador;
+    //al terminar su recorrido se queda en la meta
+    pub fn pose(&self, ticks: usize, alpha: f32) -> Option<(Vector2, f32)> {
+        let last = self.samples.len().checked_sub(1)?;
+        let (current_pos, current_a) = self.samples[ticks.min(last)];
+        let (previous_pos, previous_a) = self.samples[ticks.saturating_sub(1).min(last)];
+        let alpha = if ticks > last { 1.0 } else { alpha };
+
+        Some((
+            Vector2::new(
+                previous_pos.x + (current_pos.x - previous_pos.x) * alpha,
+                previous_pos.y + (current_pos.y - previous_pos.y) * alpha,
+            ),
+            previous_a + (current_a - previous_a) * alpha,
+        ))
+    }
+
+    //  #ghost 1
+    //  time = 42.3
+    //  [samples]
+    //  150 150 1.5707964     (x, y, ángulo por paso)
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = format!("#ghost 1\ntime = {}\n[samples]\n", self.time);
+        for (pos, a) in &self.samples {
+            contents.push_str(&format!("{} {} {}\n", pos.x, pos.y, a));
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut ghost = Ghost::default();
+        let mut in_samples = false;
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: String| format!("{}:{}: {}", path, line_number + 1, message);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line == "[samples]" {
+                in_samples = true;
+            } else if !in_samples {
+                let (key, value) = line.split_once('=').ok_or_else(|| error("expected 'key = value'".to_string()))?;
+                match key.trim() {
+                    "time" => ghost.time = value.trim().parse().map_err(|_| error(format!("invalid time '{}'", value.trim())))?,
+                    other => return Err(error(format!("unknown key '{}'", other))),
+                }
+            } else {
+                let numbers = line.split_whitespace()
+                    .map(|text| text.parse::<f32>().map_err(|_| error(format!("invalid number '{}'", text))))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if numbers.len() != 3 {
+                    return Err(error(format!("expected 'x y angle', found {} values", numbers.len())));
+                }
+                ghost.samples.push((Vector2::new(numbers[0], numbers[1]), numbers[2]));
+            }
+        }
+
+        Ok(ghost)
+    }
+}
+
+//Guarda la trayectoria si es la mejor del nivel; devuelve si se reemplazó
+pub fn save_if_best(level_key: &str, run: &Ghost, best: Option<&Ghost>) -> bool {
+    if best.is_some_and(|best| best.time <= run.time) {
+        return false;
+    }
+
+    let path = ghost_path(level_key);
+    match run.save(&path) {
+        Ok(()) => true,
+        Err(e) => {
+            println!("No se pudo guardar {}: {}", path, e);
+            false
+        },
+    }
+}
@@ -0,0 +1,239 @@
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use crate::framebuffer::Framebuffer;
+use crate::game_state::GameManager;
+use crate::generator::generate_level;
+use crate::level::{load_level, validate_level};
+use crate::player::Player;
+use crate::objectives::ObjectiveState;
+use crate::items::Inventory;
+use crate::settings::MapMode;
+use crate::replay::ReplayEvent;
+use crate::sprites::SpriteManager;
+use crate::stats::{RunStats, format_time};
+use crate::{apply_level_textures, draw_minimap, load_difficulty_level, render_maze, render_world, reset_level, simulate_step, MapOverlays};
+
+//Semilla fija para que los sprites aparezcan siempre en el mismo lugar
+const SPRITE_SEED: u64 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeadlessView {
+    World,   //Vista 3D
+    Maze,    //Vista 2D
+    Minimap, //Solo el minimapa
+}
+
+//Renderizado sin ventana: carga un nivel, coloca al jugador y guarda un solo frame en PNG
+pub struct HeadlessOptions {
+    pub output: String,
+    pub level: String, //Archivo del nivel o "random" para usar el generador
+    pub pose: Option<(f32, f32, f32)>, //Celda x, celda y (admite decimales) y ángulo en grados
+    pub view: HeadlessView,
+    pub width: u32,
+    pub height: u32,
+    pub sprites: bool,
+    pub tick: Option<usize>, //Con --replay, paso hasta el que se simula antes de dibujar
+}
+
+//Separa las opciones del modo sin ventana del resto de argumentos:
+//  --headless salida.png  --level archivo|random  --pose X Y GRADOS
+//  --view 3d|2d|minimap  --resolution WxH  --no-sprites  --tick N (con --replay)
+pub fn split_headless_args(args: &[String]) -> Result<(Option<HeadlessOptions>, Vec<String>), String> {
+    let mut options = HeadlessOptions {
+        output: String::new(),
+        level: "maze_easy.txt".to_string(),
+        pose: None,
+        view: HeadlessView::World,
+        width: 1300,
+        height: 900,
+        sprites: true,
+        tick: None,
+    };
+    let mut headless = false;
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--headless" => {
+                options.output = value()?.clone();
+                headless = true;
+            },
+            "--level" => options.level = value()?.clone(),
+            "--pose" => {
+                let mut coordinate = || -> Result<f32, String> {
+                    let text = value()?;
+                    text.parse().map_err(|_| format!("invalid value '{}' for --pose", text))
+                };
+                options.pose = Some((coordinate()?, coordinate()?, coordinate()?));
+            },
+            "--view" => {
+                options.view = match value()?.to_lowercase().as_str() {
+                    "3d" => HeadlessView::World,
+                    "2d" => HeadlessView::Maze,
+                    "minimap" => HeadlessView::Minimap,
+                    other => return Err(format!("unknown view '{}'", other)),
+                };
+            },
+            "--resolution" => {
+                let size = value()?;
+                let (w, h) = size.split_once('x').ok_or(format!("invalid resolution '{}'", size))?;
+                options.width = w.parse().map_err(|_| format!("invalid width '{}'", w))?;
+                options.height = h.parse().map_err(|_| format!("invalid height '{}'", h))?;
+            },
+            "--no-sprites" => options.sprites = false,
+            "--tick" => {
+                let text = value()?;
+                options.tick = Some(text.parse().map_err(|_| format!("invalid tick '{}' for --tick", text))?);
+            },
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    Ok((if headless { Some(options) } else { None }, rest))
+}
+
+pub fn run_headless(options: &HeadlessOptions, game_manager: &GameManager, block_size: usize) -> Result<(), String> {
+    //Una repetición (--replay) decide el nivel por su cuenta
+    let mut level = if game_manager.playback.is_some() {
+        load_difficulty_level(&game_manager.current_difficulty).map_err(|e| e.to_string())?
+    } else if options.level == "random" {
+        let mut level = generate_level(&game_manager.random_options);
+        validate_level(&mut level, 0).map_err(|e| e.to_string())?;
+        level
+    } else {
+        load_level(&options.level).map_err(|e| e.to_string())?
+    };
+
+    let mut framebuffer = Framebuffer::new(options.width, options.height);
+    apply_level_textures(&level, &mut framebuffer);
+
+    let mut player = Player {
+        pos: Vector2::new(0.0, 0.0),
+        a: PI / 3.0,
+        fov: PI / 3.0,
+        inventory: Inventory::default(),
+    };
+    let mut sprite_manager = SpriteManager::new();
+    fastrand::seed(game_manager.playback.as_ref().map_or(SPRITE_SEED, |p| p.replay.seed));
+    reset_level(&mut level, &mut player, &mut sprite_manager, &mut framebuffer, block_size);
+
+    //Simular la repetición hasta el paso pedido, hasta la meta o hasta el final
+    if let Some(ref playback) = game_manager.playback {
+        let mut playback = playback.clone();
+        let mut run = RunStats::default();
+        let last_tick = options.tick.unwrap_or(usize::MAX);
+
+        while playback.ticks_played() < last_tick {
+            match playback.next_event() {
+                Some(ReplayEvent::Tick(input)) => {
+                    simulate_step(&mut run, &mut level, &mut player, &mut sprite_manager, &input, block_size);
+                    match level.objectives.check(&player, &run, &level.maze, block_size) {
+                        ObjectiveState::Won => {
+                            println!("Objectives completed at tick {}", playback.ticks_played());
+                            break;
+                        },
+                        ObjectiveState::Lost(reason) => {
+                            println!("Defeat at tick {}: {}", playback.ticks_played(), reason.reason());
+                            break;
+                        },
+                        ObjectiveState::Running => {},
+                    }
+                },
+                Some(ReplayEvent::Reset) => {
+                    run.resets += 1;
+                    reset_level(&mut level, &mut player, &mut sprite_manager, &mut framebuffer, block_size);
+                },
+                None => break,
+            }
+        }
+
+        println!(
+            "Replay: {} ticks, time {} (recorded: {}), distance {:.1}, resets {}, caught {}",
+            playback.ticks_played(),
+            format_time(run.elapsed),
+            playback.replay.time.map_or("-".to_string(), format_time),
+            run.distance,
+            run.resets,
+            run.catches,
+        );
+    }
+
+    if let Some((x, y, degrees)) = options.pose {
+        player.pos = Vector2::new(x * block_size as f32, y * block_size as f32);
+        player.a = degrees.to_radians();
+    }
+
+    framebuffer.clear();
+    //Las capturas muestran el laberinto completo, sin niebla
+    let overlays = MapOverlays { ghost: None, hint: Vec::new(), enemies: sprite_manager.enemy_markers(), map: MapMode::Full };
+    match options.view {
+        HeadlessView::World => {
+            render_world(&mut framebuffer, &level.maze, &level.doors, &mut level.exploration, block_size, &player);
+            if options.sprites {
+                sprite_manager.load_sprite_frames();
+                sprite_manager.render_sprites_3d(&mut framebuffer, &player, block_size);
+            }
+        },
+        HeadlessView::Maze => render_maze(&mut framebuffer, &level.maze, &level.doors, &level.exploration, block_size, &player, &overlays),
+        HeadlessView::Minimap => draw_minimap(&mut framebuffer, &level.maze, &level.doors, &level.exploration, &player, &overlays, block_size),
+    }
+
+    framebuffer.render_to_file(&options.output)?;
+    println!("Saved frame to {}", options.output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Imagen de referencia del raycaster: maze_easy desde la celda (1.5, 1.5) mirando a 15° por el pasillo,
+    //sin sprites ni texturas (colores de respaldo), para que no dependa de los assets.
+    //Para regenerarla después de un cambio intencional: UPDATE_GOLDEN=1 cargo test golden
+    const GOLDEN: &str = "tests/golden/easy_3d.png";
+    const BLOCK_SIZE: usize = 100;
+    //Diferencia por canal que se tolera entre plataformas (sin/cos no son bit a bit iguales)
+    const CHANNEL_TOLERANCE: u8 = 2;
+
+    fn render_easy() -> Framebuffer {
+        let mut level = load_level("maze_easy.txt").unwrap();
+        let mut framebuffer = Framebuffer::new(320, 240);
+        let mut player = Player {
+            pos: Vector2::zero(),
+            a: PI / 3.0,
+            fov: PI / 3.0,
+            inventory: Inventory::default(),
+        };
+        let mut sprite_manager = SpriteManager::new();
+        fastrand::seed(SPRITE_SEED);
+        reset_level(&mut level, &mut player, &mut sprite_manager, &mut framebuffer, BLOCK_SIZE);
+
+        player.pos = Vector2::new(1.5 * BLOCK_SIZE as f32, 1.5 * BLOCK_SIZE as f32);
+        player.a = 15.0_f32.to_radians();
+        framebuffer.clear();
+        render_world(&mut framebuffer, &level.maze, &level.doors, &mut level.exploration, BLOCK_SIZE, &player);
+        framebuffer
+    }
+
+    #[test]
+    fn golden_easy_3d() {
+        let framebuffer = render_easy();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            framebuffer.render_to_file(GOLDEN).unwrap();
+            return;
+        }
+
+        let golden = image::open(GOLDEN).unwrap().to_rgba8();
+        assert_eq!((golden.width(), golden.height()), (framebuffer.width, framebuffer.height));
+
+        let different = golden.as_raw().chunks_exact(4)
+            .zip(framebuffer.color_buffer.chunks_exact(4))
+            .filter(|(expected, actual)| expected.iter().zip(actual.iter()).any(|(e, a)| e.abs_diff(*a) > CHANNEL_TOLERANCE))
+            .count();
+        //Unos pocos píxeles en los bordes de las paredes pueden caer del otro lado
+        let allowed = (framebuffer.width * framebuffer.height / 1000) as usize;
+        assert!(different <= allowed, "{} píxeles distintos de {} (se permiten {})", different, GOLDEN, allowed);
+    }
+}
@@ -0,0 +1,519 @@
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+
+pub const INPUT_CONFIG: &str = "input.cfg";
+
+//Un eje del gamepad cuenta como "presionado" (para menús) a partir de este valor
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+//Umbrales para reconocer un eje o el mouse al reasignar controles
+const CAPTURE_AXIS_THRESHOLD: f32 = 0.6;
+const CAPTURE_MOUSE_THRESHOLD: f32 = 15.0;
+
+//Acciones del juego; cada una puede tener varios enlaces de teclado, mouse o gamepad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    ToggleMap,
+    ToggleHint,
+    Use,
+    Reset,
+    Menu,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    Confirm,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::ToggleMap,
+        Action::ToggleHint,
+        Action::Use,
+        Action::Reset,
+        Action::Menu,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::Confirm,
+        Action::Quit,
+    ];
+
+    //Nombre usado en el archivo de configuración
+    pub fn config_name(&self) -> &str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::ToggleMap => "toggle_map",
+            Action::ToggleHint => "toggle_hint",
+            Action::Use => "use",
+            Action::Reset => "reset",
+            Action::Menu => "menu",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right",
+            Action::Confirm => "confirm",
+            Action::Quit => "quit",
+        }
+    }
+
+    //Nombre mostrado en la pantalla de controles
+    pub fn get_name(&self) -> &str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBackward => "Move backward",
+            Action::StrafeLeft => "Strafe left",
+            Action::StrafeRight => "Strafe right",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::ToggleMap => "Toggle map",
+            Action::ToggleHint => "Show hint",
+            Action::Use => "Use door/switch",
+            Action::Reset => "Reset level",
+            Action::Menu => "Menu",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::MenuLeft => "Menu left",
+            Action::MenuRight => "Menu right",
+            Action::Confirm => "Confirm",
+            Action::Quit => "Quit",
+        }
+    }
+
+    fn from_config_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.config_name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyboardKey),
+    Mouse(MouseButton),
+    MouseX(f32), //Movimiento horizontal del mouse en la dirección indicada (+1 o -1)
+    Button(GamepadButton),
+    Axis(GamepadAxis, f32), //Eje del gamepad en la dirección indicada (+1 o -1)
+}
+
+const MOUSE_BUTTONS: [MouseButton; 7] = [
+    MouseButton::MOUSE_BUTTON_LEFT,
+    MouseButton::MOUSE_BUTTON_RIGHT,
+    MouseButton::MOUSE_BUTTON_MIDDLE,
+    MouseButton::MOUSE_BUTTON_SIDE,
+    MouseButton::MOUSE_BUTTON_EXTRA,
+    MouseButton::MOUSE_BUTTON_FORWARD,
+    MouseButton::MOUSE_BUTTON_BACK,
+];
+
+const GAMEPAD_BUTTONS: [GamepadButton; 17] = [
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_1,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_TRIGGER_2,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_1,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_TRIGGER_2,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE,
+    GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT,
+    GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB,
+    GamepadButton::GAMEPAD_BUTTON_RIGHT_THUMB,
+];
+
+const GAMEPAD_AXES: [GamepadAxis; 6] = [
+    GamepadAxis::GAMEPAD_AXIS_LEFT_X,
+    GamepadAxis::GAMEPAD_AXIS_LEFT_Y,
+    GamepadAxis::GAMEPAD_AXIS_RIGHT_X,
+    GamepadAxis::GAMEPAD_AXIS_RIGHT_Y,
+    GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER,
+    GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER,
+];
+
+//Nombre corto de una constante de raylib, por ejemplo KEY_W -> W
+fn short_name(value: impl std::fmt::Debug, prefix: &str) -> String {
+    let name = format!("{:?}", value);
+    name.strip_prefix(prefix).unwrap_or(&name).to_string()
+}
+
+fn sign_text(sign: f32) -> &'static str {
+    if sign < 0.0 { "-" } else { "+" }
+}
+
+fn parse_sign(text: Option<&str>) -> Result<f32, String> {
+    match text {
+        Some("+") => Ok(1.0),
+        Some("-") => Ok(-1.0),
+        _ => Err("axis direction must be + or -".to_string()),
+    }
+}
+
+impl Binding {
+    //Texto del enlace, el mismo para el archivo y para la pantalla de controles
+    pub fn describe(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("key {}", short_name(key, "KEY_")),
+            Binding::Mouse(button) => format!("mouse {}", short_name(button, "MOUSE_BUTTON_")),
+            Binding::MouseX(sign) => format!("mouse_x {}", sign_text(*sign)),
+            Binding::Button(button) => format!("button {}", short_name(button, "GAMEPAD_BUTTON_")),
+            Binding::Axis(axis, sign) => format!("axis {} {}", short_name(axis, "GAMEPAD_AXIS_"), sign_text(*sign)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Binding, String> {
+        let mut parts = text.split_whitespace();
+        let kind = parts.next().ok_or("empty binding")?;
+        let name = parts.next().map(|n| n.to_uppercase());
+
+        let binding = match kind {
+            "key" => {
+                let name = name.ok_or("missing key name")?;
+                //Recorrer los códigos conocidos por raylib y comparar por nombre
+                let key = (0..400)
+                    .filter_map(key_from_i32)
+                    .find(|key| short_name(key, "KEY_") == name)
+                    .ok_or(format!("unknown key '{}'", name))?;
+                Binding::Key(key)
+            },
+            "mouse" => {
+                let name = name.ok_or("missing mouse button")?;
+                let button = MOUSE_BUTTONS.iter()
+                    .copied()
+                    .find(|button| short_name(button, "MOUSE_BUTTON_") == name)
+                    .ok_or(format!("unknown mouse button '{}'", name))?;
+                Binding::Mouse(button)
+            },
+            "mouse_x" => Binding::MouseX(parse_sign(name.as_deref())?),
+            "button" => {
+                let name = name.ok_or("missing gamepad button")?;
+                let button = GAMEPAD_BUTTONS.iter()
+                    .copied()
+                    .find(|button| short_name(button, "GAMEPAD_BUTTON_") == name)
+                    .ok_or(format!("unknown gamepad button '{}'", name))?;
+                Binding::Button(button)
+            },
+            "axis" => {
+                let name = name.ok_or("missing gamepad axis")?;
+                let axis = GAMEPAD_AXES.iter()
+                    .copied()
+                    .find(|axis| short_name(axis, "GAMEPAD_AXIS_") == name)
+                    .ok_or(format!("unknown gamepad axis '{}'", name))?;
+                Binding::Axis(axis, parse_sign(parts.next())?)
+            },
+            _ => return Err(format!("unknown binding type '{}'", kind)),
+        };
+
+        Ok(binding)
+    }
+}
+
+//Asignación de acciones a enlaces más los ajustes de sensibilidad
+#[derive(Debug, Clone)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+    pub deadzone: f32,          //Zona muerta de los sticks (0..1)
+    pub mouse_sensitivity: f32, //Radianes por píxel del mouse; viene de settings.cfg
+    pub stick_sensitivity: f32, //Multiplicador de los ejes del gamepad
+}
+
+impl InputMap {
+    pub fn default_bindings() -> Self {
+        use Binding::*;
+        use KeyboardKey::*;
+        use GamepadAxis::*;
+        use GamepadButton::*;
+
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::MoveForward, vec![Key(KEY_W), Key(KEY_UP), Axis(GAMEPAD_AXIS_LEFT_Y, -1.0)]);
+        bindings.insert(Action::MoveBackward, vec![Key(KEY_S), Key(KEY_DOWN), Axis(GAMEPAD_AXIS_LEFT_Y, 1.0)]);
+        bindings.insert(Action::StrafeLeft, vec![Key(KEY_A), Axis(GAMEPAD_AXIS_LEFT_X, -1.0)]);
+        bindings.insert(Action::StrafeRight, vec![Key(KEY_D), Axis(GAMEPAD_AXIS_LEFT_X, 1.0)]);
+        bindings.insert(Action::TurnLeft, vec![Key(KEY_LEFT), MouseX(-1.0), Axis(GAMEPAD_AXIS_RIGHT_X, -1.0)]);
+        bindings.insert(Action::TurnRight, vec![Key(KEY_RIGHT), MouseX(1.0), Axis(GAMEPAD_AXIS_RIGHT_X, 1.0)]);
+        bindings.insert(Action::ToggleMap, vec![Key(KEY_M), Button(GAMEPAD_BUTTON_RIGHT_FACE_UP)]);
+        bindings.insert(Action::ToggleHint, vec![Key(KEY_H), Button(GAMEPAD_BUTTON_RIGHT_FACE_LEFT)]);
+        bindings.insert(Action::Use, vec![Key(KEY_E), Key(KEY_SPACE), Button(GAMEPAD_BUTTON_RIGHT_TRIGGER_1)]);
+        bindings.insert(Action::Reset, vec![Key(KEY_R), Button(GAMEPAD_BUTTON_MIDDLE_LEFT)]);
+        bindings.insert(Action::Menu, vec![Key(KEY_Q), Button(GAMEPAD_BUTTON_MIDDLE_RIGHT)]);
+        bindings.insert(Action::MenuUp, vec![Key(KEY_UP), Axis(GAMEPAD_AXIS_LEFT_Y, -1.0)]);
+        bindings.insert(Action::MenuDown, vec![Key(KEY_DOWN), Axis(GAMEPAD_AXIS_LEFT_Y, 1.0)]);
+        bindings.insert(Action::MenuLeft, vec![Key(KEY_LEFT), Axis(GAMEPAD_AXIS_LEFT_X, -1.0)]);
+        bindings.insert(Action::MenuRight, vec![Key(KEY_RIGHT), Axis(GAMEPAD_AXIS_LEFT_X, 1.0)]);
+        bindings.insert(Action::Confirm, vec![Key(KEY_ENTER), Button(GAMEPAD_BUTTON_RIGHT_FACE_DOWN)]);
+        bindings.insert(Action::Quit, vec![Button(GAMEPAD_BUTTON_RIGHT_FACE_RIGHT)]);
+
+        InputMap {
+            bindings,
+            deadzone: 0.1,
+            mouse_sensitivity: 0.01,
+            stick_sensitivity: 1.0,
+        }
+    }
+
+    //Carga el archivo de controles sobre los valores por defecto. Formato:
+    //  deadzone = 0.1
+    //  move_forward = key W, key UP, axis LEFT_Y -
+    //Una acción presente en el archivo reemplaza todos sus enlaces por defecto
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut map = InputMap::default_bindings();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            if let Err(e) = map.apply_line(line) {
+                println!("{}:{}: {}", path, line_number + 1, e);
+            }
+        }
+
+        Ok(map)
+    }
+
+    pub fn load_or_default(path: &str) -> Self {
+        match InputMap::load(path) {
+            Ok(map) => map,
+            Err(_) => InputMap::default_bindings(),
+        }
+    }
+
+    fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (key, value) = line.split_once('=').ok_or("expected 'name = value'")?;
+        let (key, value) = (key.trim(), value.trim());
+        let number = || value.parse::<f32>().map_err(|_| format!("invalid number '{}'", value));
+
+        match key {
+            "deadzone" => self.deadzone = number()?.clamp(0.0, 0.95),
+            "stick_sensitivity" => self.stick_sensitivity = number()?,
+            "mouse_sensitivity" => {}, //Archivos antiguos: ahora se guarda en settings.cfg
+            _ => {
+                let action = Action::from_config_name(key).ok_or(format!("unknown action '{}'", key))?;
+                let bindings = value.split(',')
+                    .map(str::trim)
+                    .filter(|text| !text.is_empty())
+                    .map(Binding::parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.bindings.insert(action, bindings);
+            },
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let mut contents = String::from("#Controles del juego (se puede editar desde el menú CONTROLS)\n");
+        contents.push_str(&format!("deadzone = {}\n", self.deadzone));
+        contents.push_str(&format!("stick_sensitivity = {}\n", self.stick_sensitivity));
+
+        for action in Action::ALL {
+            let bindings: Vec<String> = self.bindings(action).iter().map(Binding::describe).collect();
+            contents.push_str(&format!("{} = {}\n", action.config_name(), bindings.join(", ")));
+        }
+
+        fs::write(path, contents)
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn add_binding(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn clear_bindings(&mut self, action: Action) {
+        self.bindings.insert(action, Vec::new());
+    }
+
+    //Todos los enlaces de una acción en una línea, para la pantalla de controles
+    pub fn describe(&self, action: Action) -> String {
+        let bindings: Vec<String> = self.bindings(action).iter().map(Binding::describe).collect();
+        if bindings.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            bindings.join(", ")
+        }
+    }
+
+    //Primera tecla asignada (o el primer enlace), para los textos de ayuda
+    pub fn key_name(&self, action: Action) -> String {
+        let bindings = self.bindings(action);
+        let binding = bindings.iter()
+            .find(|binding| matches!(binding, Binding::Key(_)))
+            .or(bindings.first());
+
+        match binding {
+            Some(Binding::Key(key)) => short_name(key, "KEY_"),
+            Some(binding) => binding.describe(),
+            None => "-".to_string(),
+        }
+    }
+}
+
+//Estado de las acciones en el frame actual
+pub struct InputState {
+    pub map: InputMap,
+    values: HashMap<Action, f32>,
+    down: HashMap<Action, bool>,
+    previous: HashMap<Action, bool>,
+    look: f32,
+}
+
+impl InputState {
+    pub fn new(map: InputMap) -> Self {
+        InputState {
+            map,
+            values: HashMap::new(),
+            down: HashMap::new(),
+            previous: HashMap::new(),
+            look: 0.0,
+        }
+    }
+
+    //Leer todos los dispositivos una vez por frame
+    pub fn update(&mut self, rl: &RaylibHandle) {
+        let gamepad_available = rl.is_gamepad_available(0);
+        let mouse_delta = rl.get_mouse_delta();
+        self.previous = std::mem::take(&mut self.down);
+        self.look = 0.0;
+
+        for action in Action::ALL {
+            let mut value: f32 = 0.0;
+            let mut down = false;
+
+            for binding in self.map.bindings(action) {
+                match *binding {
+                    Binding::Key(key) => {
+                        if rl.is_key_down(key) {
+                            value = value.max(1.0);
+                            down = true;
+                        }
+                    },
+                    Binding::Mouse(button) => {
+                        if rl.is_mouse_button_down(button) {
+                            value = value.max(1.0);
+                            down = true;
+                        }
+                    },
+                    Binding::MouseX(sign) => {
+                        //El mouse da un desplazamiento, no una velocidad: se acumula aparte
+                        let amount = (mouse_delta.x * sign).max(0.0) * self.map.mouse_sensitivity;
+                        self.look += match action {
+                            Action::TurnLeft => -amount,
+                            _ => amount,
+                        };
+                    },
+                    Binding::Button(button) => {
+                        if gamepad_available && rl.is_gamepad_button_down(0, button) {
+                            value = value.max(1.0);
+                            down = true;
+                        }
+                    },
+                    Binding::Axis(axis, sign) => {
+                        if gamepad_available {
+                            let amount = rl.get_gamepad_axis_movement(0, axis) * sign;
+                            if amount > self.map.deadzone {
+                                value = value.max(amount * self.map.stick_sensitivity);
+                            }
+                            down |= amount > AXIS_PRESS_THRESHOLD;
+                        }
+                    },
+                }
+            }
+
+            self.values.insert(action, value);
+            self.down.insert(action, down);
+        }
+    }
+
+    //Intensidad de la acción: 1 para botones, proporcional para ejes
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.down.get(&action).copied().unwrap_or(false)
+    }
+
+    //Verdadero solo en el frame en que la acción empieza
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.is_down(action) && !self.previous.get(&action).copied().unwrap_or(false)
+    }
+
+    //Diferencia entre dos acciones opuestas, por ejemplo avanzar y retroceder
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+
+    //Giro en radianes producido por el mouse en este frame
+    pub fn look(&self) -> f32 {
+        self.look
+    }
+}
+
+//Devuelve el primer input nuevo del frame para asignarlo a una acción
+pub fn capture_binding(rl: &mut RaylibHandle) -> Option<Binding> {
+    if let Some(key) = rl.get_key_pressed() {
+        return Some(Binding::Key(key));
+    }
+
+    for button in MOUSE_BUTTONS {
+        if rl.is_mouse_button_pressed(button) {
+            return Some(Binding::Mouse(button));
+        }
+    }
+
+    let mouse_delta = rl.get_mouse_delta();
+    if mouse_delta.x.abs() > CAPTURE_MOUSE_THRESHOLD {
+        return Some(Binding::MouseX(mouse_delta.x.signum()));
+    }
+
+    if rl.is_gamepad_available(0) {
+        for button in GAMEPAD_BUTTONS {
+            if rl.is_gamepad_button_pressed(0, button) {
+                return Some(Binding::Button(button));
+            }
+        }
+
+        for axis in GAMEPAD_AXES {
+            let amount = rl.get_gamepad_axis_movement(0, axis);
+            //Los gatillos reposan en -1, solo cuentan al presionarlos
+            let is_trigger = matches!(axis, GamepadAxis::GAMEPAD_AXIS_LEFT_TRIGGER | GamepadAxis::GAMEPAD_AXIS_RIGHT_TRIGGER);
+            if is_trigger && amount < 0.0 {
+                continue;
+            }
+            if amount.abs() > CAPTURE_AXIS_THRESHOLD {
+                return Some(Binding::Axis(axis, amount.signum()));
+            }
+        }
+    }
+
+    None
+}
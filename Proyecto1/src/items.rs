@@ -0,0 +1,120 @@
+//Objetos que se recogen al pasar cerca; se dibujan como sprites y van al inventario del jugador
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Key,       //Abre una puerta con llave
+    Scroll,    //Coleccionable; el nivel puede pedir varios para que cuente la meta
+    TimeBonus, //Resta segundos al tiempo de la partida al recogerlo
+    MapReveal, //Muestra en el minimapa el camino completo hasta la meta
+}
+
+//Segundos que descuenta cada bonificación de tiempo
+pub const TIME_BONUS: f32 = 10.0;
+
+impl ItemKind {
+    pub const ALL: [ItemKind; 4] = [ItemKind::Key, ItemKind::Scroll, ItemKind::TimeBonus, ItemKind::MapReveal];
+
+    //Carácter en la cuadrícula; al cargar el nivel la celda queda libre y el objeto pasa a ser un sprite
+    pub fn cell(&self) -> char {
+        match self {
+            ItemKind::Key => 'k',
+            ItemKind::Scroll => 'p',
+            ItemKind::TimeBonus => 't',
+            ItemKind::MapReveal => 'm',
+        }
+    }
+
+    pub fn from_cell(cell: char) -> Option<ItemKind> {
+        ItemKind::ALL.into_iter().find(|kind| kind.cell() == cell)
+    }
+
+    //Nombre en los archivos de nivel
+    pub fn get_name(&self) -> &str {
+        match self {
+            ItemKind::Key => "key",
+            ItemKind::Scroll => "scroll",
+            ItemKind::TimeBonus => "time",
+            ItemKind::MapReveal => "map",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ItemKind> {
+        ItemKind::ALL.into_iter().find(|kind| kind.get_name() == name)
+    }
+
+    //Nombre en el HUD
+    pub fn label(&self) -> &str {
+        match self {
+            ItemKind::Key => "Keys",
+            ItemKind::Scroll => "Scrolls",
+            ItemKind::TimeBonus => "Time bonuses",
+            ItemKind::MapReveal => "Maps",
+        }
+    }
+}
+
+//Objetos que lleva el jugador, contados por tipo
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Inventory {
+    counts: [u32; ItemKind::ALL.len()],
+}
+
+impl Inventory {
+    pub fn count(&self, kind: ItemKind) -> u32 {
+        self.counts[kind as usize]
+    }
+
+    pub fn add(&mut self, kind: ItemKind) {
+        self.counts[kind as usize] += 1;
+    }
+
+    //Gasta uno si hay; devuelve si se pudo
+    pub fn take(&mut self, kind: ItemKind) -> bool {
+        if self.counts[kind as usize] == 0 {
+            return false;
+        }
+        self.counts[kind as usize] -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_spends_one() {
+        let mut inventory = Inventory::default();
+        inventory.add(ItemKind::Key);
+        inventory.add(ItemKind::Key);
+        inventory.add(ItemKind::Scroll);
+
+        assert!(inventory.take(ItemKind::Key));
+        assert_eq!(inventory.count(ItemKind::Key), 1);
+        //Los otros tipos no cambian
+        assert_eq!(inventory.count(ItemKind::Scroll), 1);
+    }
+
+    #[test]
+    fn take_does_not_underflow() {
+        let mut inventory = Inventory::default();
+        assert!(!inventory.take(ItemKind::Key));
+        assert_eq!(inventory.count(ItemKind::Key), 0);
+
+        inventory.add(ItemKind::TimeBonus);
+        assert!(inventory.take(ItemKind::TimeBonus));
+        assert!(!inventory.take(ItemKind::TimeBonus));
+        assert_eq!(inventory.count(ItemKind::TimeBonus), 0);
+    }
+
+    #[test]
+    fn cells_and_names_round_trip() {
+        for kind in ItemKind::ALL {
+            assert_eq!(ItemKind::from_cell(kind.cell()), Some(kind));
+            assert_eq!(ItemKind::from_name(kind.get_name()), Some(kind));
+        }
+        //Las celdas del laberinto no son objetos
+        for cell in [' ', 'g', '+', '-', '|', 'D', 'L', 'S'] {
+            assert_eq!(ItemKind::from_cell(cell), None);
+        }
+    }
+}
@@ -0,0 +1,822 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use crate::maze::Maze;
+use crate::pathfinding::distance_field;
+use crate::doors::{Doors, DOOR, LOCKED_DOOR, SWITCH, is_door};
+use crate::items::ItemKind;
+use crate::objectives::{Objectives, Objective, FailCondition};
+use crate::exploration::Exploration;
+use crate::fog::Fog;
+
+//Versión más reciente del formato de nivel que entiende el cargador
+pub const LEVEL_FORMAT_VERSION: u32 = 1;
+
+//Caracteres que el juego sabe interpretar; los declarados con "texture" también son válidos
+pub const KNOWN_CELLS: &[char] = &[' ', '+', '-', '|', 'g', DOOR, LOCKED_DOOR, SWITCH];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelErrorKind {
+    Io(String),
+    InvalidHeader(String),
+    EmptyGrid,
+    RaggedRow { expected: usize, found: usize },
+    UnknownCell(char),
+    NoGoal,
+    SpawnOutOfBounds,
+    SpawnInWall(char),
+    UnreachableGoal,
+    EnemyOutOfBounds,
+    EnemyInWall(char),
+    NotASwitch(char),
+    NotADoor(char),
+    LockedWithoutKey,
+    NotEnoughItems { kind: ItemKind, needed: u32, available: u32 },
+}
+
+//Error de carga con la posición (línea y columna, desde 1) dentro del archivo
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelError {
+    pub file: String,
+    pub position: Option<(usize, usize)>,
+    pub kind: LevelErrorKind,
+}
+
+impl LevelError {
+    fn new(file: &str, position: Option<(usize, usize)>, kind: LevelErrorKind) -> Self {
+        LevelError {
+            file: file.to_string(),
+            position,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some((line, column)) = self.position {
+            write!(f, " line {} col {}", line, column)?;
+        }
+        write!(f, ": ")?;
+
+        match &self.kind {
+            LevelErrorKind::Io(e) => write!(f, "cannot read file ({})", e),
+            LevelErrorKind::InvalidHeader(e) => write!(f, "{}", e),
+            LevelErrorKind::EmptyGrid => write!(f, "the level has no grid"),
+            LevelErrorKind::RaggedRow { expected, found } => {
+                write!(f, "row has {} cells, expected {} (use padding = <cell>)", found, expected)
+            },
+            LevelErrorKind::UnknownCell(c) => write!(f, "unknown cell '{}'", c),
+            LevelErrorKind::NoGoal => write!(f, "the level has no goal 'g'"),
+            LevelErrorKind::SpawnOutOfBounds => write!(f, "spawn is outside the grid"),
+            LevelErrorKind::SpawnInWall(c) => write!(f, "spawn is inside a wall '{}'", c),
+            LevelErrorKind::UnreachableGoal => write!(f, "no goal can be reached from spawn"),
+            LevelErrorKind::EnemyOutOfBounds => write!(f, "enemy route leaves the grid"),
+            LevelErrorKind::EnemyInWall(c) => write!(f, "enemy route goes through a wall '{}'", c),
+            LevelErrorKind::NotASwitch(c) => write!(f, "switch must be on a '{}' cell, found '{}'", SWITCH, c),
+            LevelErrorKind::NotADoor(c) => write!(f, "switch target must be a door, found '{}'", c),
+            LevelErrorKind::LockedWithoutKey => write!(f, "the level has locked doors but no keys"),
+            LevelErrorKind::NotEnoughItems { kind, needed, available } => {
+                write!(f, "the goal needs {} {} items but the level only has {}", needed, kind.get_name(), available)
+            },
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+//Reglas para distribuir sprites en el laberinto
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteSpawnRules {
+    pub count: usize,
+    pub min_spacing: usize, //Separación mínima entre sprites, en celdas
+    pub positions: Vec<(usize, usize)>, //Celdas fijas declaradas en el nivel
+    pub enemies: usize, //Enemigos con ronda aleatoria además de los declarados
+    pub routes: Vec<Vec<(usize, usize)>>, //Rondas fijas; la primera celda es donde aparece el enemigo
+    pub items: Vec<(ItemKind, usize)>, //Objetos en celdas aleatorias además de los fijos
+}
+
+impl Default for SpriteSpawnRules {
+    fn default() -> Self {
+        SpriteSpawnRules {
+            count: 8,
+            min_spacing: 3,
+            positions: Vec::new(),
+            enemies: 0,
+            routes: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+}
+
+//Qué pasa cuando un enemigo alcanza al jugador
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CatchRule {
+    Restart,      //Vuelve al spawn con el nivel reiniciado; el tiempo sigue corriendo
+    Penalty(f32), //Segundos que se suman al tiempo de la partida
+}
+
+//Interruptor y las puertas que mueve
+pub type SwitchLinks = ((usize, usize), Vec<(usize, usize)>);
+
+//Línea del archivo (desde 1) de cada declaración del encabezado, para ubicar los errores
+//de validación; las listas siguen el orden de las de Level
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderLines {
+    pub spawn: Option<usize>,
+    pub goals: Vec<usize>,
+    pub enemies: Vec<usize>,
+    pub items: Vec<usize>,
+    pub objectives: Vec<Option<usize>>, //La meta implícita no tiene línea
+    pub switches: Vec<usize>,
+}
+
+pub struct Level {
+    pub version: u32,
+    pub file: String, //Archivo del que se cargó; los errores lo nombran aunque el nivel tenga "name"
+    pub name: String,
+    pub author: String,
+    pub spawn_cell: (usize, usize),
+    pub spawn_angle: f32,
+    pub theme: Option<String>,
+    pub textures: Vec<(char, String)>,
+    pub music: Option<String>,
+    pub fog: Option<Fog>, //Niebla por distancia en la vista 3D
+    pub sprites: SpriteSpawnRules,
+    pub caught: CatchRule,
+    pub goals: Vec<(usize, usize)>, //Celdas meta extra además de las 'g' de la cuadrícula
+    pub items: Vec<(ItemKind, (usize, usize))>, //Objetos fijos, de la cuadrícula o del encabezado
+    pub objectives: Objectives, //Condiciones para ganar y para perder
+    pub switches: Vec<SwitchLinks>,
+    pub padding: Option<char>, //Relleno para filas más cortas que la más larga
+    pub lines: HeaderLines,
+    pub maze: Maze,
+    pub doors: Doors, //Estado de puertas e interruptores durante la partida
+    pub exploration: Exploration, //Celdas que el jugador ya vio durante la partida
+}
+
+impl Level {
+    //Nivel sin encabezado: los valores que antes estaban fijos en main.rs
+    pub fn from_maze(name: &str, maze: Maze) -> Self {
+        Level {
+            version: 0,
+            file: name.to_string(),
+            name: name.to_string(),
+            author: String::new(),
+            spawn_cell: (1, 1),
+            spawn_angle: PI / 3.0,
+            theme: None,
+            textures: Vec::new(),
+            music: None,
+            fog: None,
+            sprites: SpriteSpawnRules::default(),
+            caught: CatchRule::Restart,
+            goals: Vec::new(),
+            items: Vec::new(),
+            objectives: Objectives::default(),
+            switches: Vec::new(),
+            padding: None,
+            lines: HeaderLines::default(),
+            maze,
+            doors: Doors::default(),
+            exploration: Exploration::default(),
+        }
+    }
+
+    //Objetos de un tipo en el nivel, fijos y aleatorios
+    pub fn item_count(&self, kind: ItemKind) -> u32 {
+        let placed = self.items.iter().filter(|&&(k, _)| k == kind).count();
+        let random: usize = self.sprites.items.iter().filter(|&&(k, _)| k == kind).map(|&(_, n)| n).sum();
+        (placed + random) as u32
+    }
+}
+
+//Carga un nivel. El formato con encabezado empieza con "#level <versión>",
+//seguido de líneas "clave = valor" y luego "[grid]" con el laberinto:
+//
+//  #level 1
+//  name = Bosque
+//  author = Fernando
+//  spawn = 1 1 east
+//  theme = assets/themes/default.theme
+//  texture = g assets/img/konoha.jpg
+//  music = assets/sounds/menu.mp3
+//  fog = 170 190 170 0.15
+//  sprites = 8 3
+//  sprite = 5 3
+//  enemies = 1
+//  enemy = 4 1 4 5 7 5
+//  caught = penalty 10
+//  time_limit = 120
+//  objective = collect scroll 3
+//  objective = survive 60
+//  fail = caught 3
+//  goal = 5 1
+//  item = scroll 2 1
+//  items = scroll 4
+//  require = scroll 5
+//  key = 3 1
+//  switch = 6 3 4 3 4 5
+//  padding = +
+//  [grid]
+//  +--+--+
+//  |    g|
+//  +--+--+
+//
+//Los archivos sin encabezado se leen completos como laberinto.
+//El nivel se valida antes de devolverlo, ver validate_level
+pub fn load_level(filename: &str) -> Result<Level, LevelError> {
+    let io_error = |e: std::io::Error| LevelError::new(filename, None, LevelErrorKind::Io(e.to_string()));
+
+    let file = File::open(filename).map_err(io_error)?;
+    let reader = BufReader::new(file);
+    let lines = reader.lines().collect::<Result<Vec<String>, _>>().map_err(io_error)?;
+
+    parse_level(filename, &lines)
+}
+
+pub fn parse_level(name: &str, lines: &[String]) -> Result<Level, LevelError> {
+    let mut level = Level::from_maze(name, Vec::new());
+
+    //Índice (desde 0) de la primera línea de la cuadrícula dentro del archivo
+    let grid_start = match lines.first().and_then(|line| line.trim().strip_prefix("#level")) {
+        Some(version) => {
+            level.version = version.trim().parse::<u32>().map_err(|_| {
+                LevelError::new(name, Some((1, 1)), LevelErrorKind::InvalidHeader(
+                    format!("invalid format version '{}'", version.trim()),
+                ))
+            })?;
+            if level.version > LEVEL_FORMAT_VERSION {
+                println!("Advertencia: {} usa la versión {} del formato (soportada: {})",
+                    name, level.version, LEVEL_FORMAT_VERSION);
+            }
+            parse_header(&mut level, lines)?
+        },
+        //Formato antiguo: solo el laberinto
+        None => 0,
+    };
+
+    //Ignorar líneas vacías al final del archivo
+    let mut grid_end = lines.len();
+    while grid_end > grid_start && lines[grid_end - 1].trim().is_empty() {
+        grid_end -= 1;
+    }
+
+    level.maze = lines[grid_start..grid_end]
+        .iter()
+        .map(|line| line.chars().collect())
+        .collect();
+
+    //Las metas declaradas en el encabezado se marcan en la cuadrícula
+    for (i, &(x, y)) in level.goals.iter().enumerate() {
+        match level.maze.get_mut(y).and_then(|row| row.get_mut(x)) {
+            Some(cell) => *cell = 'g',
+            None => {
+                return Err(LevelError::new(name, header_position(level.lines.goals.get(i).copied()), LevelErrorKind::InvalidHeader(
+                    format!("goal {} {} is outside the grid", x, y),
+                )));
+            }
+        }
+    }
+
+    //Los objetos de la cuadrícula se vuelven sprites y su celda queda libre
+    for (y, row) in level.maze.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            if let Some(kind) = ItemKind::from_cell(*cell) {
+                *cell = ' ';
+                level.items.push((kind, (x, y)));
+            }
+        }
+    }
+
+    validate_level(&mut level, grid_start)?;
+    Ok(level)
+}
+
+//Lee las líneas "clave = valor" y devuelve el índice de la primera línea de la cuadrícula
+fn parse_header(level: &mut Level, lines: &[String]) -> Result<usize, LevelError> {
+    for (index, line) in lines.iter().enumerate().skip(1) {
+        let trimmed = line.trim();
+        if trimmed == "[grid]" {
+            return Ok(index + 1);
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        apply_header_line(level, trimmed, index + 1).map_err(|e| {
+            LevelError::new(&level.file, Some((index + 1, 1)), LevelErrorKind::InvalidHeader(e))
+        })?;
+    }
+
+    Err(LevelError::new(&level.file, None, LevelErrorKind::InvalidHeader(
+        "missing [grid] section".to_string(),
+    )))
+}
+
+//Comprueba que el laberinto sea jugable: forma rectangular (o relleno explícito),
+//solo caracteres conocidos, al menos una meta, spawn libre y meta alcanzable
+pub fn validate_level(level: &mut Level, grid_start: usize) -> Result<(), LevelError> {
+    let name = level.file.clone();
+    let position = |x: usize, y: usize| Some((grid_start + y + 1, x + 1));
+
+    if level.maze.is_empty() {
+        return Err(LevelError::new(&name, None, LevelErrorKind::EmptyGrid));
+    }
+
+    //Forma rectangular
+    let width = level.maze.iter().map(|row| row.len()).max().unwrap_or(0);
+    for (y, row) in level.maze.iter_mut().enumerate() {
+        if row.len() < width {
+            match level.padding {
+                Some(padding) => row.resize(width, padding),
+                None => {
+                    return Err(LevelError::new(&name, position(row.len(), y), LevelErrorKind::RaggedRow {
+                        expected: width,
+                        found: row.len(),
+                    }));
+                }
+            }
+        }
+    }
+
+    //Sin un objetivo de llegar a algún lado (o de sobrevivir) se gana en una 'g'
+    if !level.objectives.goals.iter().any(Objective::is_destination) {
+        level.objectives.goals.insert(0, Objective::ReachGoal);
+        level.lines.objectives.insert(0, None);
+    }
+    let needs_goal = level.objectives.goals.contains(&Objective::ReachGoal);
+
+    //Caracteres conocidos
+    let mut goals = Vec::new();
+    for (y, row) in level.maze.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            let declared = level.textures.iter().any(|&(c, _)| c == cell);
+            if !KNOWN_CELLS.contains(&cell) && !declared {
+                return Err(LevelError::new(&name, position(x, y), LevelErrorKind::UnknownCell(cell)));
+            }
+            if cell == 'g' {
+                goals.push((x, y));
+            }
+        }
+    }
+
+    if needs_goal && goals.is_empty() {
+        return Err(LevelError::new(&name, None, LevelErrorKind::NoGoal));
+    }
+
+    //Spawn dentro de la cuadrícula y en una celda libre
+    let (spawn_x, spawn_y) = level.spawn_cell;
+    let spawn_cell = match level.maze.get(spawn_y).and_then(|row| row.get(spawn_x)) {
+        Some(&cell) => cell,
+        None => return Err(LevelError::new(&name, header_position(level.lines.spawn), LevelErrorKind::SpawnOutOfBounds)),
+    };
+    if !is_walkable(spawn_cell) {
+        return Err(LevelError::new(&name, position(spawn_x, spawn_y), LevelErrorKind::SpawnInWall(spawn_cell)));
+    }
+
+    //Las rondas de los enemigos solo pasan por celdas libres
+    for (i, route) in level.sprites.routes.iter().enumerate() {
+        for &(x, y) in route {
+            match level.maze.get(y).and_then(|row| row.get(x)) {
+                Some(&cell) if is_walkable(cell) => {},
+                Some(&cell) => return Err(LevelError::new(&name, position(x, y), LevelErrorKind::EnemyInWall(cell))),
+                None => return Err(LevelError::new(
+                    &name,
+                    header_position(level.lines.enemies.get(i).copied()),
+                    LevelErrorKind::EnemyOutOfBounds,
+                )),
+            }
+        }
+    }
+
+    //Interruptores sobre celdas 'S' y que muevan solo puertas
+    for (i, ((x, y), targets)) in level.switches.iter().enumerate() {
+        let line = header_position(level.lines.switches.get(i).copied());
+        match level.maze.get(*y).and_then(|row| row.get(*x)) {
+            Some(&SWITCH) => {},
+            Some(&cell) => return Err(LevelError::new(&name, position(*x, *y), LevelErrorKind::NotASwitch(cell))),
+            None => return Err(LevelError::new(&name, line, LevelErrorKind::InvalidHeader(
+                format!("switch {} {} is outside the grid", x, y),
+            ))),
+        }
+        for &(door_x, door_y) in targets {
+            match level.maze.get(door_y).and_then(|row| row.get(door_x)) {
+                Some(&cell) if is_door(cell) => {},
+                Some(&cell) => return Err(LevelError::new(&name, position(door_x, door_y), LevelErrorKind::NotADoor(cell))),
+                None => return Err(LevelError::new(&name, line, LevelErrorKind::InvalidHeader(
+                    format!("door {} {} is outside the grid", door_x, door_y),
+                ))),
+            }
+        }
+    }
+
+    //Solo los objetos del encabezado pueden fallar: los de la cuadrícula dejan su celda libre
+    for (i, &(kind, (x, y))) in level.items.iter().enumerate() {
+        match level.maze.get(y).and_then(|row| row.get(x)) {
+            Some(&cell) if is_walkable(cell) => {},
+            _ => return Err(LevelError::new(&name, header_position(level.lines.items.get(i).copied()), LevelErrorKind::InvalidHeader(
+                format!("{} {} {} is not on a free cell", kind.get_name(), x, y),
+            ))),
+        }
+    }
+
+    //Se señala la primera puerta con llave
+    let locked = level.maze.iter().enumerate()
+        .find_map(|(y, row)| row.iter().position(|&cell| cell == LOCKED_DOOR).map(|x| (x, y)));
+    if let Some((x, y)) = locked && level.item_count(ItemKind::Key) == 0 {
+        return Err(LevelError::new(&name, position(x, y), LevelErrorKind::LockedWithoutKey));
+    }
+
+    //Alguna meta alcanzable desde el spawn; las puertas cuentan como paso porque se pueden abrir
+    let from_spawn = distance_field(&level.maze, &[level.spawn_cell], is_passable);
+    if needs_goal && !goals.iter().any(|&goal| from_spawn.get(goal).is_some()) {
+        let (goal_x, goal_y) = goals[0];
+        return Err(LevelError::new(&name, position(goal_x, goal_y), LevelErrorKind::UnreachableGoal));
+    }
+
+    for (i, objective) in level.objectives.goals.iter().enumerate() {
+        let line = header_position(level.lines.objectives.get(i).copied().flatten());
+        match *objective {
+            //Las celdas objetivo tienen que estar libres y ser alcanzables
+            Objective::ReachCell((x, y)) => match level.maze.get(y).and_then(|row| row.get(x)) {
+                Some(&cell) if is_walkable(cell) => {
+                    if from_spawn.get((x, y)).is_none() {
+                        return Err(LevelError::new(&name, position(x, y), LevelErrorKind::UnreachableGoal));
+                    }
+                },
+                _ => return Err(LevelError::new(&name, line, LevelErrorKind::InvalidHeader(
+                    format!("objective cell {} {} is not on a free cell", x, y),
+                ))),
+            },
+            //No se pueden pedir más objetos de los que hay en el nivel
+            Objective::Collect(kind, needed) => {
+                let available = level.item_count(kind);
+                if needed > available {
+                    return Err(LevelError::new(&name, line, LevelErrorKind::NotEnoughItems { kind, needed, available }));
+                }
+            },
+            Objective::ReachGoal | Objective::Survive(_) => {},
+        }
+    }
+
+    level.doors = Doors::from_level(level);
+    Ok(())
+}
+
+//Posición de una declaración del encabezado; sin línea (valores por defecto) no hay posición
+fn header_position(line: Option<usize>) -> Option<(usize, usize)> {
+    line.map(|line| (line, 1))
+}
+
+pub fn is_walkable(cell: char) -> bool {
+    cell == ' ' || cell == 'g'
+}
+
+//Caminable o una puerta, que se puede abrir
+pub fn is_passable(cell: char) -> bool {
+    is_walkable(cell) || is_door(cell)
+}
+
+fn apply_header_line(level: &mut Level, line: &str, line_number: usize) -> Result<(), String> {
+    let (key, value) = line.split_once('=').ok_or("expected 'key = value'")?;
+    let key = key.trim();
+    let value = value.trim();
+
+    match key {
+        "name" => level.name = value.to_string(),
+        "author" => level.author = value.to_string(),
+        "spawn" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() < 2 {
+                return Err("spawn needs 'x y [facing]'".to_string());
+            }
+            level.spawn_cell = (parse_number(parts[0])?, parse_number(parts[1])?);
+            if let Some(facing) = parts.get(2) {
+                level.spawn_angle = parse_facing(facing)?;
+            }
+            level.lines.spawn = Some(line_number);
+        },
+        "theme" => level.theme = Some(value.to_string()),
+        "texture" => {
+            let mut chars = value.chars();
+            let cell = chars.next().ok_or("missing texture cell")?;
+            let path = chars.as_str().trim();
+            if path.is_empty() {
+                return Err(format!("missing image for '{}'", cell));
+            }
+            level.textures.push((cell, path.to_string()));
+        },
+        "music" => level.music = Some(value.to_string()),
+        "fog" => level.fog = if value == "off" { None } else { Some(Fog::parse(value)?) },
+        "sprites" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if let Some(count) = parts.first() {
+                level.sprites.count = parse_number(count)?;
+            }
+            if let Some(spacing) = parts.get(1) {
+                level.sprites.min_spacing = parse_number(spacing)?;
+            }
+        },
+        "sprite" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 2 {
+                return Err("sprite needs 'x y'".to_string());
+            }
+            level.sprites.positions.push((parse_number(parts[0])?, parse_number(parts[1])?));
+        },
+        "enemies" => level.sprites.enemies = parse_number(value)?,
+        "enemy" => {
+            let numbers = value.split_whitespace().map(parse_number).collect::<Result<Vec<_>, _>>()?;
+            if numbers.is_empty() || numbers.len() % 2 != 0 {
+                return Err("enemy needs 'x y' for each cell of its route".to_string());
+            }
+            level.sprites.routes.push(numbers.chunks(2).map(|cell| (cell[0], cell[1])).collect());
+            level.lines.enemies.push(line_number);
+        },
+        "caught" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            level.caught = match parts.as_slice() {
+                ["restart"] => CatchRule::Restart,
+                ["penalty", seconds] => CatchRule::Penalty(parse_seconds(seconds)?),
+                _ => return Err("caught needs 'restart' or 'penalty <seconds>'".to_string()),
+            };
+        },
+        "key" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 2 {
+                return Err("key needs 'x y'".to_string());
+            }
+            level.items.push((ItemKind::Key, (parse_number(parts[0])?, parse_number(parts[1])?)));
+            level.lines.items.push(line_number);
+        },
+        "item" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 3 {
+                return Err("item needs '<kind> x y'".to_string());
+            }
+            let kind = parse_item_kind(parts[0])?;
+            level.items.push((kind, (parse_number(parts[1])?, parse_number(parts[2])?)));
+            level.lines.items.push(line_number);
+        },
+        "items" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 2 {
+                return Err("items needs '<kind> <count>'".to_string());
+            }
+            level.sprites.items.push((parse_item_kind(parts[0])?, parse_number(parts[1])?));
+        },
+        "require" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 2 {
+                return Err("require needs '<kind> <count>'".to_string());
+            }
+            level.objectives.goals.push(Objective::Collect(parse_item_kind(parts[0])?, parse_number(parts[1])? as u32));
+            level.lines.objectives.push(Some(line_number));
+        },
+        "switch" => {
+            let numbers = value.split_whitespace().map(parse_number).collect::<Result<Vec<_>, _>>()?;
+            if numbers.len() < 4 || numbers.len() % 2 != 0 {
+                return Err("switch needs 'x y' followed by 'x y' for each door".to_string());
+            }
+            let doors = numbers[2..].chunks(2).map(|cell| (cell[0], cell[1])).collect();
+            level.switches.push(((numbers[0], numbers[1]), doors));
+            level.lines.switches.push(line_number);
+        },
+        "goal" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            if parts.len() != 2 {
+                return Err("goal needs 'x y'".to_string());
+            }
+            level.goals.push((parse_number(parts[0])?, parse_number(parts[1])?));
+            level.lines.goals.push(line_number);
+        },
+        "padding" => {
+            let mut chars = value.chars();
+            let cell = chars.next().ok_or("missing padding cell")?;
+            if chars.next().is_some() {
+                return Err(format!("padding must be a single cell, got '{}'", value));
+            }
+            level.padding = Some(cell);
+        },
+        "time_limit" => {
+            let seconds = parse_seconds(value)?;
+            if seconds > 0.0 {
+                level.objectives.fails.push(FailCondition::TimeLimit(seconds));
+            }
+        },
+        "objective" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            let objective = match parts.as_slice() {
+                ["goal"] => Objective::ReachGoal,
+                ["cell", x, y] => Objective::ReachCell((parse_number(x)?, parse_number(y)?)),
+                ["collect", kind, count] => Objective::Collect(parse_item_kind(kind)?, parse_number(count)? as u32),
+                ["survive", seconds] => Objective::Survive(parse_seconds(seconds)?),
+                _ => return Err("objective needs 'goal', 'cell x y', 'collect <kind> <count>' or 'survive <seconds>'".to_string()),
+            };
+            level.objectives.goals.push(objective);
+            level.lines.objectives.push(Some(line_number));
+        },
+        "fail" => {
+            let parts: Vec<&str> = value.split_whitespace().collect();
+            let fail = match parts.as_slice() {
+                ["time", seconds] => FailCondition::TimeLimit(parse_seconds(seconds)?),
+                ["caught", count] => FailCondition::Caught(parse_number(count)? as u32),
+                _ => return Err("fail needs 'time <seconds>' or 'caught <count>'".to_string()),
+            };
+            level.objectives.fails.push(fail);
+        },
+        _ => return Err(format!("unknown key '{}'", key)),
+    }
+
+    Ok(())
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value.parse::<usize>().map_err(|_| format!("invalid number '{}'", value))
+}
+
+fn parse_seconds(value: &str) -> Result<f32, String> {
+    value.parse::<f32>().map_err(|_| format!("invalid time '{}'", value))
+}
+
+fn parse_item_kind(value: &str) -> Result<ItemKind, String> {
+    ItemKind::from_name(value).ok_or(format!("unknown item '{}' (key, scroll, time or map)", value))
+}
+
+//Orientación inicial: punto cardinal o grados (0 = este, 90 = sur)
+fn parse_facing(value: &str) -> Result<f32, String> {
+    match value.to_lowercase().as_str() {
+        "east" | "e" => Ok(0.0),
+        "south" | "s" => Ok(PI / 2.0),
+        "west" | "w" => Ok(PI),
+        "north" | "n" => Ok(-PI / 2.0),
+        degrees => degrees
+            .parse::<f32>()
+            .map(|d| d.to_radians())
+            .map_err(|_| format!("invalid facing '{}'", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Level, LevelError> {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        parse_level("test.txt", &lines)
+    }
+
+    fn error(text: &str) -> LevelError {
+        match parse(text) {
+            Ok(_) => panic!("el nivel debería ser inválido:\n{}", text),
+            Err(e) => {
+                //El menú dibuja el mensaje con la fuente de mapa de bits, que solo tiene ASCII
+                assert!(e.to_string().is_ascii(), "{}", e);
+                e
+            },
+        }
+    }
+
+    #[test]
+    fn ragged_row() {
+        let e = error("+---+\n|g\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::RaggedRow { expected: 5, found: 2 });
+        //La columna es la primera que falta
+        assert_eq!(e.position, Some((2, 3)));
+    }
+
+    #[test]
+    fn unknown_cell() {
+        //Con encabezado la cuadrícula empieza en la línea 3
+        let e = error("#level 1\n[grid]\n+---+\n| xg|\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::UnknownCell('x'));
+        assert_eq!(e.position, Some((4, 3)));
+    }
+
+    #[test]
+    fn no_goal() {
+        let e = error("+---+\n|   |\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::NoGoal);
+        assert_eq!(e.position, None);
+    }
+
+    #[test]
+    fn spawn_out_of_bounds() {
+        //Apunta a la línea del encabezado que declaró el spawn
+        let e = error("#level 1\nspawn = 9 9\n[grid]\n+---+\n| g |\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::SpawnOutOfBounds);
+        assert_eq!(e.position, Some((2, 1)));
+    }
+
+    #[test]
+    fn spawn_in_wall() {
+        //Sin encabezado el spawn es (1, 1)
+        let e = error("+---+\n|-g |\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::SpawnInWall('-'));
+        assert_eq!(e.position, Some((2, 2)));
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let e = error("#level 1\nname = Encerrado\n\n[grid]\n+-----+\n| | g |\n+-----+");
+        assert_eq!(e.kind, LevelErrorKind::UnreachableGoal);
+        assert_eq!(e.position, Some((6, 5)));
+    }
+
+    #[test]
+    fn goal_out_of_bounds() {
+        let e = error("#level 1\ngoal = 1 1\ngoal = 8 1\n[grid]\n+---+\n|   |\n+---+");
+        assert!(matches!(e.kind, LevelErrorKind::InvalidHeader(_)));
+        assert_eq!(e.position, Some((3, 1)));
+    }
+
+    #[test]
+    fn enemy_out_of_bounds() {
+        //Apunta a la línea de la ronda que se sale, no a la primera
+        let e = error("#level 1\nenemy = 1 1 3 1\nenemy = 2 1 9 1\n[grid]\n+---+\n| g |\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::EnemyOutOfBounds);
+        assert_eq!(e.position, Some((3, 1)));
+    }
+
+    #[test]
+    fn enemy_in_wall() {
+        let e = error("#level 1\nenemy = 1 1 2 2\n[grid]\n+---+\n| g |\n+---+");
+        assert_eq!(e.kind, LevelErrorKind::EnemyInWall('-'));
+        assert_eq!(e.position, Some((6, 3)));
+    }
+
+    #[test]
+    fn switch_door_out_of_bounds() {
+        //La puerta fuera de la cuadrícula se ubica en la línea del interruptor
+        let e = error("#level 1\nswitch = 2 2 9 9\n[grid]\n+-S-+\n|  g|\n+-S-+");
+        assert!(matches!(e.kind, LevelErrorKind::InvalidHeader(_)));
+        assert_eq!(e.position, Some((2, 1)));
+    }
+
+    #[test]
+    fn locked_door_without_key() {
+        let e = error("+-----+\n|  L g|\n+-----+");
+        assert_eq!(e.kind, LevelErrorKind::LockedWithoutKey);
+        assert_eq!(e.position, Some((2, 4)));
+    }
+
+    #[test]
+    fn key_out_of_bounds() {
+        let e = error("#level 1\nkey = 1 1\nkey = 1 7\n[grid]\n+-----+\n|  L g|\n+-----+");
+        assert!(matches!(e.kind, LevelErrorKind::InvalidHeader(_)));
+        assert_eq!(e.position, Some((3, 1)));
+    }
+
+    #[test]
+    fn item_in_wall() {
+        let e = error("#level 1\nitem = scroll 1 1\nitem = map 2 0\n[grid]\n+---+\n| g |\n+---+");
+        assert!(matches!(e.kind, LevelErrorKind::InvalidHeader(_)));
+        assert_eq!(e.position, Some((3, 1)));
+    }
+
+    #[test]
+    fn not_enough_items() {
+        //Un pergamino en la cuadrícula y otro del encabezado no alcanzan para 3; la meta
+        //implícita que se agrega antes no corre la línea del require
+        let e = error("#level 1\nitem = scroll 2 1\nrequire = scroll 3\n[grid]\n+----+\n|p  g|\n+----+");
+        assert_eq!(e.kind, LevelErrorKind::NotEnoughItems { kind: ItemKind::Scroll, needed: 3, available: 2 });
+        assert_eq!(e.position, Some((3, 1)));
+    }
+
+    #[test]
+    fn objective_cell_out_of_bounds() {
+        //Apunta a la línea de la celda, no a la del primer objetivo
+        let e = error("#level 1\nobjective = collect key 0\nobjective = cell 9 1\n[grid]\n+---+\n| g |\n+---+");
+        assert!(matches!(e.kind, LevelErrorKind::InvalidHeader(_)));
+        assert_eq!(e.position, Some((3, 1)));
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        //"name" cambia el título del nivel, no el archivo que falló
+        let e = error("#level 1\nname = Encerrado\nspawn = 0 0\n[grid]\n+---+\n| g |\n+---+");
+        assert_eq!(e.file, "test.txt");
+        assert!(e.to_string().starts_with("test.txt line 5 col 1: "), "{}", e);
+    }
+
+    #[test]
+    fn valid_level() {
+        let level = parse("#level 1\nname = Bosque\nspawn = 1 1 east\n[grid]\n+---+\n|  g|\n+---+").expect("nivel válido");
+        assert_eq!(level.file, "test.txt");
+        assert_eq!(level.name, "Bosque");
+        assert_eq!(level.spawn_cell, (1, 1));
+        assert_eq!(level.maze[1][3], 'g');
+    }
+
+    #[test]
+    fn bundled_levels_load() {
+        for file in ["maze_easy.txt", "maze_medium.txt", "maze_difficult.txt", "maze_enemies.txt", "maze_doors.txt", "maze_items.txt", "maze_objectives.txt", "maze_fog.txt"] {
+            if let Err(e) = load_level(file) {
+                panic!("{}", e);
+            }
+        }
+    }
+}
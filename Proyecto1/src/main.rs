@@ -10,16 +10,49 @@ mod game_state;
 mod screens;
 mod audio;
 mod sprites;
+mod textures;
+mod level;
+mod collision;
+mod input;
+mod generator;
+mod headless;
+mod settings;
+mod stats;
+mod replay;
+mod ghost;
+mod enemy;
+mod pathfinding;
+mod doors;
+mod items;
+mod objectives;
+mod exploration;
+mod fog;
 
 use line::line;
-use maze::{Maze, load_maze};
-use caster::{cast_ray, Intersect};
+use maze::Maze;
+use caster::{cast_ray, cast_ray_from, trace_ray, column_angle, projection_plane_distance, Intersect, WallFace};
 use framebuffer::Framebuffer;
-use player::{Player, process_events, get_gamepad_info, check_gamepad_mode_change, check_victory};
+use player::{Player, PlayerInput, PLAYER_RADIUS, read_player_input, update_player, interpolate_player, get_gamepad_info, on_goal};
 use game_state::{GameManager, GameState, Difficulty};
-use screens::{draw_welcome_screen, draw_victory_screen, handle_victory_input, render_victory_screen, handle_welcome_input, VictoryAction};
+use screens::{draw_welcome_screen, draw_victory_screen, handle_victory_input, render_victory_screen, handle_welcome_input, draw_controls_screen, handle_controls_input, VictoryAction};
+use screens::{draw_pause_menu, handle_pause_input, draw_settings_screen, handle_settings_input, PauseAction};
+use screens::{draw_defeat_screen, handle_defeat_input};
+use settings::{Settings, MapMode, SETTINGS_FILE};
+use stats::{Leaderboard, RunStats, LEADERBOARD_FILE, format_time};
+use replay::{Replay, ReplayEvent, save_last_run};
+use ghost::{Ghost, ghost_path, save_if_best};
+use input::{Action, InputMap, InputState, INPUT_CONFIG};
 use audio::AudioManager;
 use sprites::SpriteManager;
+use level::{Level, LevelError, CatchRule, load_level, validate_level};
+use pathfinding::{Cell, hint_path, world_to_cell};
+use doors::{Doors, UseResult, DOOR, LOCKED_DOOR, SWITCH};
+use items::{Inventory, ItemKind, TIME_BONUS};
+use objectives::ObjectiveState;
+use exploration::Exploration;
+use generator::{Algorithm, generate_level, parse_chance};
+use headless::{split_headless_args, run_headless};
+use textures::TextureRegistry;
 use raylib::prelude::*;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -32,10 +65,29 @@ fn cell_to_color(cell: char) -> Color {
         '|' => Color::BLUE,
         'g' => Color::ORANGE, 
         ' ' => Color::BLACK,
+        DOOR => Color::BROWN,
+        LOCKED_DOOR => Color::GOLD,
+        SWITCH => Color::MAROON,
         _ => Color::WHITE,
     }
 }
 
+//Interruptor encendido o apagado
+fn switch_color(on: bool) -> Color {
+    if on { Color::LIME } else { Color::MAROON }
+}
+
+const DEFAULT_THEME: &str = "assets/themes/default.theme";
+
+fn shade_color(color: Color, factor: f32) -> Color {
+    Color::new(
+        (color.r as f32 * factor) as u8,
+        (color.g as f32 * factor) as u8,
+        (color.b as f32 * factor) as u8,
+        color.a,
+    )
+}
+
 fn draw_cell(
     framebuffer: &mut Framebuffer,
     xo: usize,
@@ -43,7 +95,16 @@ fn draw_cell(
     block_size: usize,
     cell: char,
 ) {
-    let color = cell_to_color(cell);
+    draw_cell_color(framebuffer, xo, yo, block_size, cell_to_color(cell));
+}
+
+fn draw_cell_color(
+    framebuffer: &mut Framebuffer,
+    xo: usize,
+    yo: usize,
+    block_size: usize,
+    color: Color,
+) {
     framebuffer.set_current_color(color);
     
     for x in xo..xo + block_size {
@@ -55,10 +116,42 @@ fn draw_cell(
     }
 }
 
+//Círculo con una línea de dirección, para marcar al fantasma en los mapas
+fn draw_marker(framebuffer: &mut Framebuffer, center: Vector2, radius: i32, angle: f32, length: f32, color: Color) {
+    framebuffer.set_current_color(color);
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            if dx * dx + dy * dy <= radius * radius {
+                let x = center.x as i32 + dx;
+                let y = center.y as i32 + dy;
+                if x >= 0 && y >= 0 && x < framebuffer.width as i32 && y < framebuffer.height as i32 {
+                    framebuffer.set_pixel(x as u32, y as u32);
+                }
+            }
+        }
+    }
+    
+    let end = Vector2::new(center.x + length * angle.cos(), center.y + length * angle.sin());
+    line(framebuffer, center, end);
+}
+
+//Color del fantasma de la mejor partida en los mapas
+const GHOST_COLOR: Color = Color::new(120, 200, 255, 255);
+//Color de los enemigos en los mapas
+const ENEMY_COLOR: Color = Color::new(230, 60, 200, 255);
+//Pista del minimapa: celdas que se marcan hacia la meta más cercana
+const HINT_COLOR: Color = Color::new(120, 255, 120, 255);
+//Celdas sin descubrir en la vista 2D
+const UNEXPLORED_COLOR: Color = Color::new(25, 25, 25, 255);
+const HINT_LENGTH: usize = 8;
+
 fn draw_minimap(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
+    doors: &Doors,
+    exploration: &Exploration,
     player: &Player,
+    overlays: &MapOverlays,
     block_size: usize,
 ) {
     if maze.is_empty() {
@@ -95,13 +188,20 @@ fn draw_minimap(
     //Dibujar el maze en el minimapa
     for (row_index, row) in maze.iter().enumerate() {
         for (col_index, &cell) in row.iter().enumerate() {
+            if overlays.hides((col_index, row_index), exploration) {
+                continue;
+            }
             let cell_x = minimap_x + col_index * minimap_scale;
             let cell_y = minimap_y + row_index * minimap_scale;
             
+            //Las puertas abiertas del todo se ven como pasillo
+            let cell = if doors.is_open((col_index, row_index)) { ' ' } else { cell };
             let color = match cell {
                 '+' | '-' | '|' => Color::WHITE,
                 'g' => Color::ORANGE, 
                 ' ' => Color::BLACK,
+                DOOR | LOCKED_DOOR => cell_to_color(cell),
+                SWITCH => switch_color(doors.switch_on((col_index, row_index))),
                 _ => Color::GRAY,
             };
             
@@ -120,6 +220,40 @@ fn draw_minimap(
         }
     }
     
+    //Pista: un punto en el centro de cada celda del camino hacia la meta
+    framebuffer.set_current_color(HINT_COLOR);
+    let hint_size = (minimap_scale / 2).max(2);
+    for &(hint_x, hint_y) in &overlays.hint {
+        let start_x = minimap_x + hint_x * minimap_scale + (minimap_scale - hint_size) / 2;
+        let start_y = minimap_y + hint_y * minimap_scale + (minimap_scale - hint_size) / 2;
+        for x in start_x..start_x + hint_size {
+            for y in start_y..start_y + hint_size {
+                if x < framebuffer.width as usize && y < framebuffer.height as usize {
+                    framebuffer.set_pixel(x as u32, y as u32);
+                }
+            }
+        }
+    }
+    
+    //Fantasma debajo del jugador, en el centro de su celda igual que el jugador
+    if let Some((ghost_pos, ghost_a)) = overlays.ghost {
+        let ghost_x = minimap_x + ((ghost_pos.x.max(0.0) as usize) / block_size) * minimap_scale + minimap_scale / 2;
+        let ghost_y = minimap_y + ((ghost_pos.y.max(0.0) as usize) / block_size) * minimap_scale + minimap_scale / 2;
+        let radius = (minimap_scale / 3).max(2) as i32;
+        draw_marker(framebuffer, Vector2::new(ghost_x as f32, ghost_y as f32), radius, ghost_a, 10.0, GHOST_COLOR);
+    }
+    
+    //Enemigos en su posición real, sin ajustar al centro de la celda
+    for &(enemy_pos, enemy_a) in &overlays.enemies {
+        if overlays.hides(world_to_cell(enemy_pos, block_size), exploration) {
+            continue;
+        }
+        let enemy_x = minimap_x as f32 + enemy_pos.x / block_size as f32 * minimap_scale as f32;
+        let enemy_y = minimap_y as f32 + enemy_pos.y / block_size as f32 * minimap_scale as f32;
+        let radius = (minimap_scale / 3).max(2) as i32;
+        draw_marker(framebuffer, Vector2::new(enemy_x, enemy_y), radius, enemy_a, 8.0, ENEMY_COLOR);
+    }
+    
     //Dibujar la posición del jugador
     let player_minimap_x = minimap_x + ((player.pos.x as usize) / block_size) * minimap_scale + minimap_scale / 2;
     let player_minimap_y = minimap_y + ((player.pos.y as usize) / block_size) * minimap_scale + minimap_scale / 2;
@@ -168,6 +302,7 @@ fn draw_minimap(
 fn draw_scaled_ray(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
+    doors: &Doors,
     player: &Player,
     angle: f32,
     original_block_size: usize,
@@ -175,34 +310,18 @@ fn draw_scaled_ray(
     offset_x: usize,
     offset_y: usize,
 ) {
-    let mut d = 0.0;
-    let step_size = 1.0;
+    //Recorrer la cuadrícula con DDA y dibujar el segmento escalado hasta el impacto
+    let intersect = cast_ray_from(maze, doors, player.pos, angle, original_block_size);
+    let d = intersect.distance.min(200.0);
     
-    loop {
-        let cos_a = angle.cos();
-        let sin_a = angle.sin();
-        
-        let x = player.pos.x + d * cos_a;
-        let y = player.pos.y + d * sin_a;
-        
-        let i = (x / original_block_size as f32) as usize;
-        let j = (y / original_block_size as f32) as usize;
-        
-        if j >= maze.len() || i >= maze[j].len() || maze[j][i] != ' ' || d > 200.0 {
-            break;
-        }
-        
-        //Dibujar punto escalado
-        let scaled_x = (offset_x as f32 + x * scale_factor) as i32;
-        let scaled_y = (offset_y as f32 + y * scale_factor) as i32;
-        
-        if scaled_x >= 0 && scaled_y >= 0 && 
-           scaled_x < framebuffer.width as i32 && scaled_y < framebuffer.height as i32 {
-            framebuffer.set_pixel(scaled_x as u32, scaled_y as u32);
-        }
-        
-        d += step_size;
-    }
+    let end_x = player.pos.x + d * angle.cos();
+    let end_y = player.pos.y + d * angle.sin();
+    
+    line(
+        framebuffer,
+        Vector2::new(offset_x as f32 + player.pos.x * scale_factor, offset_y as f32 + player.pos.y * scale_factor),
+        Vector2::new(offset_x as f32 + end_x * scale_factor, offset_y as f32 + end_y * scale_factor),
+    );
 }
 
 fn calculate_adaptive_block_size(maze: &Maze, framebuffer: &Framebuffer) -> usize {
@@ -231,8 +350,11 @@ fn calculate_adaptive_block_size(maze: &Maze, framebuffer: &Framebuffer) -> usiz
 pub fn render_maze(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
+    doors: &Doors,
+    exploration: &Exploration,
     block_size: usize,
     player: &Player,
+    overlays: &MapOverlays,
 ) {
     //Calcular el tamaño de bloque adaptativo para modo 2D
     let adaptive_block_size = calculate_adaptive_block_size(maze, framebuffer);
@@ -251,10 +373,49 @@ pub fn render_maze(
         for (col_index, &cell) in row.iter().enumerate() {
             let xo = offset_x + col_index * adaptive_block_size;
             let yo = offset_y + row_index * adaptive_block_size;
-            draw_cell(framebuffer, xo, yo, adaptive_block_size, cell);
+            if overlays.hides((col_index, row_index), exploration) {
+                draw_cell_color(framebuffer, xo, yo, adaptive_block_size, UNEXPLORED_COLOR);
+            } else if cell == SWITCH {
+                draw_cell_color(framebuffer, xo, yo, adaptive_block_size, switch_color(doors.switch_on((col_index, row_index))));
+            } else if let Some(door) = doors.get((col_index, row_index)) {
+                //Hoja de la puerta inserta en la celda, del largo de la parte todavía cerrada
+                draw_cell(framebuffer, xo, yo, adaptive_block_size, ' ');
+                let thickness = (adaptive_block_size / 5).max(2);
+                let length = ((1.0 - door.open) * adaptive_block_size as f32) as usize;
+                let inset = (adaptive_block_size - thickness) / 2;
+                let start = adaptive_block_size - length;
+                framebuffer.set_current_color(cell_to_color(cell));
+                for a in start..adaptive_block_size {
+                    for b in inset..inset + thickness {
+                        let (x, y) = if door.horizontal { (xo + a, yo + b) } else { (xo + b, yo + a) };
+                        if x < framebuffer.width as usize && y < framebuffer.height as usize {
+                            framebuffer.set_pixel(x as u32, y as u32);
+                        }
+                    }
+                }
+            } else {
+                draw_cell(framebuffer, xo, yo, adaptive_block_size, cell);
+            }
         }
     }
     
+    //Fantasma de la mejor partida
+    if let Some((ghost_pos, ghost_a)) = overlays.ghost {
+        let center = Vector2::new(offset_x as f32 + ghost_pos.x * scale_factor, offset_y as f32 + ghost_pos.y * scale_factor);
+        let radius = (8.0 * scale_factor).max(6.0) as i32;
+        draw_marker(framebuffer, center, radius, ghost_a, 30.0 * scale_factor, GHOST_COLOR);
+    }
+    
+    //Enemigos
+    for &(enemy_pos, enemy_a) in &overlays.enemies {
+        if overlays.hides(world_to_cell(enemy_pos, block_size), exploration) {
+            continue;
+        }
+        let center = Vector2::new(offset_x as f32 + enemy_pos.x * scale_factor, offset_y as f32 + enemy_pos.y * scale_factor);
+        let radius = (8.0 * scale_factor).max(6.0) as i32;
+        draw_marker(framebuffer, center, radius, enemy_a, 30.0 * scale_factor, ENEMY_COLOR);
+    }
+    
     //Dibujar al jugador como un círculo rojo
     framebuffer.set_current_color(Color::RED);
     
@@ -300,71 +461,163 @@ pub fn render_maze(
         let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
         
         //Usar una versión modificada de cast_ray que dibuja líneas escaladas
-        draw_scaled_ray(framebuffer, &maze, &player, a, block_size, scale_factor, offset_x, offset_y);
+        draw_scaled_ray(framebuffer, maze, doors, player, a, block_size, scale_factor, offset_x, offset_y);
+    }
+}
+
+fn render_floor_and_ceiling(
+    framebuffer: &mut Framebuffer,
+    block_size: usize,
+    player: &Player,
+) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let hh = height as f32 / 2.0;
+    let block = block_size as f32;
+    
+    let distance_to_projection_plane = projection_plane_distance(player.fov, width);
+    
+    //Los ojos del jugador están a media altura de la pared
+    let eye_height = block / 2.0;
+    
+    //Dirección de la cámara y vector del plano de proyección (hacia la derecha)
+    let dir_x = player.a.cos();
+    let dir_y = player.a.sin();
+    let plane_x = -dir_y / distance_to_projection_plane;
+    let plane_y = dir_x / distance_to_projection_plane;
+    let first_offset = 0.5 - width as f32 / 2.0;
+    let fog = framebuffer.fog;
+    
+    //Recorrer cada fila debajo del horizonte; el techo es su reflejo
+    for y in (hh as u32)..height {
+        let row_offset = y as f32 + 0.5 - hh;
+        if row_offset <= 0.0 {
+            continue;
+        }
+        
+        //Distancia perpendicular del punto del piso visto en esta fila
+        let row_distance = eye_height * distance_to_projection_plane / row_offset;
+        let ceiling_y = height - 1 - y;
+        
+        //La niebla es la misma en toda la fila; pasado el plano lejano no hace falta leer texturas
+        let fog_amount = fog.map_or(0.0, |fog| fog.amount(row_distance / block));
+        if let Some(fog) = fog && row_distance / block >= fog.far_plane() {
+            framebuffer.set_current_color(fog.color);
+            for x in 0..width {
+                framebuffer.set_pixel(x, y);
+                framebuffer.set_pixel(x, ceiling_y);
+            }
+            continue;
+        }
+        
+        //Punto del mundo de la primera columna y paso entre columnas
+        let mut world_x = player.pos.x + row_distance * (dir_x + plane_x * first_offset);
+        let mut world_y = player.pos.y + row_distance * (dir_y + plane_y * first_offset);
+        let step_x = row_distance * plane_x;
+        let step_y = row_distance * plane_y;
+        
+        for x in 0..width {
+            let cell_x = world_x / block;
+            let cell_y = world_y / block;
+            let tx = cell_x - cell_x.floor();
+            let ty = cell_y - cell_y.floor();
+            
+            let mut floor_color = framebuffer.get_floor_texture_pixel(tx, ty);
+            if let Some(fog) = fog {
+                floor_color = fog.mix(floor_color, fog_amount);
+            }
+            framebuffer.set_current_color(floor_color);
+            framebuffer.set_pixel(x, y);
+            
+            if ceiling_y < y {
+                let mut ceiling_color = framebuffer.get_ceiling_texture_pixel(tx, ty);
+                if let Some(fog) = fog {
+                    ceiling_color = fog.mix(ceiling_color, fog_amount);
+                }
+                framebuffer.set_current_color(ceiling_color);
+                framebuffer.set_pixel(x, ceiling_y);
+            }
+            
+            world_x += step_x;
+            world_y += step_y;
+        }
     }
 }
 
 fn render_world(
     framebuffer: &mut Framebuffer,
     maze: &Maze,
+    doors: &Doors,
+    exploration: &mut Exploration,
     block_size: usize,
     player: &Player,
 ) {
     let num_rays = framebuffer.width;
     let hh = framebuffer.height as f32 / 2.0;
     
+    //Distancia al plano de proyección derivada del FOV y del ancho de la ventana
+    let distance_to_projection_plane = projection_plane_distance(player.fov, framebuffer.width);
+    
+    //Piso y techo primero, las paredes se dibujan encima
+    render_floor_and_ceiling(framebuffer, block_size, player);
+    
+    //Con niebla los rayos se detienen en el plano lejano, donde todo es color de niebla
+    let fog = framebuffer.fog;
+    let far_plane = fog.map_or(f32::INFINITY, |fog| fog.far_plane() * block_size as f32);
+    
     for i in 0..num_rays {
-        let current_ray = i as f32 / num_rays as f32;
-        let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
-        let intersect = cast_ray(framebuffer, &maze, &player, a, block_size, false);
+        //Espaciar los rayos uniformemente sobre el plano de proyección, no en ángulo
+        let a = column_angle(player, i, framebuffer.width);
+        let max_distance = far_plane / (a - player.a).cos();
+        //Las celdas que atraviesa el rayo quedan descubiertas en los mapas
+        let intersect = trace_ray(maze, doors, player.pos, a, block_size, max_distance, |cell| exploration.mark(cell));
         
-        let distance_to_wall = intersect.distance.max(1.0); 
-        let distance_to_projection_plane = 70.0;
-        let stake_height = (hh / distance_to_wall) * distance_to_projection_plane;
-        
-        let stake_top = ((hh - (stake_height / 2.0)) as usize).max(0);
-        let stake_bottom = ((hh + (stake_height / 2.0)) as usize).min(framebuffer.height as usize);
+        //Distancia perpendicular al plano de cámara para corregir el efecto ojo de pez
+        let perpendicular_distance = intersect.distance * (a - player.a).cos();
+        framebuffer.z_buffer[i as usize] = perpendicular_distance;
         
-        //Renderizar piso con color sólido
-        for y in stake_bottom..framebuffer.height as usize {
-            if i < framebuffer.width && y < framebuffer.height as usize {
-                //Color sólido para el piso
-                let floor_color = Color::new(192, 201, 135, 255);
-                framebuffer.set_current_color(floor_color);
-                framebuffer.set_pixel(i, y as u32);
-            }
+        //Sin pared antes del plano lejano: el piso y el techo de fondo ya son niebla
+        if intersect.impact == ' ' {
+            continue;
         }
+        let fog_amount = fog.map_or(0.0, |fog| fog.amount(perpendicular_distance / block_size as f32));
+        let distance_to_wall = perpendicular_distance.max(1.0); 
+        let stake_height = (block_size as f32 / distance_to_wall) * distance_to_projection_plane;
+        
+        //Borde superior sin recortar, para que la textura no se comprima al acercarse
+        let unclipped_top = hh - (stake_height / 2.0);
+        let stake_top = (unclipped_top as usize).max(0);
+        let stake_bottom = ((hh + (stake_height / 2.0)) as usize).min(framebuffer.height as usize);
         
         //Renderizar la columna vertical con validaciones
         if stake_top < stake_bottom && stake_bottom > 0 {
             for y in stake_top..stake_bottom {
                 if i < framebuffer.width && y < framebuffer.height as usize {
                     //Calcular la coordenada Y de la textura de forma segura
-                    let wall_height = stake_bottom - stake_top;
-                    let ty = if wall_height > 0 {
-                        ((y - stake_top) as f32 / wall_height as f32).clamp(0.0, 1.0)
+                    let ty = if stake_height > 0.0 {
+                        ((y as f32 - unclipped_top) / stake_height).clamp(0.0, 1.0)
                     } else {
                         0.0
                     };
                     
-                    //Obtener el color con validaciones
-                    let color = match intersect.impact {
-                        '+' | '-' | '|' => {
-                            //Usar textura uniforme para todas las paredes
-                            let safe_tx = intersect.tx.clamp(0.0, 1.0);
-                            framebuffer.get_texture_pixel(safe_tx, ty)
-                        },
-                        'g' => {
-                            //Usar textura de Konoha para paredes de meta
-                            let safe_tx = intersect.tx.clamp(0.0, 1.0);
-                            framebuffer.get_goal_texture_pixel(safe_tx, ty)
-                        },
-                        _ => {
-                            //Usar color sólido para otros tipos de celdas
-                            cell_to_color(intersect.impact)
-                        }
+                    //Obtener el color de la textura registrada para esta celda
+                    let safe_tx = intersect.tx.clamp(0.0, 1.0);
+                    let color = if intersect.impact == SWITCH {
+                        //El interruptor muestra si está encendido
+                        let (cell_x, cell_y) = intersect.cell;
+                        switch_color(doors.switch_on((cell_x as usize, cell_y as usize)))
+                    } else {
+                        framebuffer
+                            .get_wall_texture_pixel(intersect.impact, safe_tx, ty)
+                            .unwrap_or_else(|| cell_to_color(intersect.impact)) //Color sólido si no hay textura
                     };
                     
+                    //Sombrear según la orientación de la cara golpeada y aclarar hacia la niebla
+                    let mut color = shade_color(color, intersect.face.shade());
+                    if let Some(fog) = fog {
+                        color = fog.mix(color, fog_amount);
+                    }
+                    
                     framebuffer.set_current_color(color);
                     framebuffer.set_pixel(i, y as u32);
                 }
@@ -373,84 +626,493 @@ fn render_world(
     }
 }
 
+//Cargar las texturas del nivel: tema por defecto, tema del nivel y texturas sueltas.
+//La niebla del nivel también queda en el framebuffer
+fn apply_level_textures(level: &Level, framebuffer: &mut Framebuffer) {
+    framebuffer.fog = level.fog;
+    framebuffer.textures = TextureRegistry::new();
+    if let Err(e) = framebuffer.textures.load_theme(DEFAULT_THEME) {
+        println!("No se pudo cargar el tema de texturas: {}", e);
+    }
+    
+    if let Some(ref theme) = level.theme {
+        if let Err(e) = framebuffer.textures.load_theme(theme) {
+            println!("No se pudo cargar el tema {}: {}", theme, e);
+        }
+    }
+    
+    for (cell, path) in &level.textures {
+        if let Err(e) = framebuffer.textures.apply_directive(&format!("wall {} {}", cell, path)) {
+            println!("{}: {}", level.file, e);
+        }
+    }
+}
+
+fn load_difficulty_level(difficulty: &Difficulty) -> Result<Level, LevelError> {
+    match difficulty {
+        Difficulty::Random(options) => {
+            //La misma semilla siempre genera el mismo laberinto
+            let mut level = generate_level(options);
+            validate_level(&mut level, 0).map(|_| level)
+        },
+        _ => load_level(difficulty.get_maze_file().unwrap_or_default()),
+    }
+}
+
+//Cargar el nivel de la dificultad actual; si falla, volver al menú mostrando el error
+fn load_selected_level(game_manager: &mut GameManager) -> Option<Level> {
+    match load_difficulty_level(&game_manager.current_difficulty) {
+        Ok(level) => Some(level),
+        Err(e) => {
+            println!("Error cargando nivel: {}", e);
+            game_manager.reset_to_welcome();
+            game_manager.load_error = Some(e.to_string());
+            None
+        }
+    }
+}
+
+//Colocar al jugador y los sprites según el nivel
+fn reset_level(
+    level: &mut Level,
+    player: &mut Player,
+    sprite_manager: &mut SpriteManager,
+    framebuffer: &mut Framebuffer,
+    block_size: usize,
+) {
+    spawn_level(level, player, sprite_manager, block_size);
+    framebuffer.set_background_color(Color::new(50, 50, 100, 255));
+}
+
+//Jugador en el spawn con el inventario vacío, sprites, objetos y enemigos en sus
+//posiciones iniciales, puertas cerradas y el mapa sin descubrir
+fn spawn_level(level: &mut Level, player: &mut Player, sprite_manager: &mut SpriteManager, block_size: usize) {
+    sprite_manager.spawn_sprites_with_rules(&level.maze, block_size, &level.sprites);
+    sprite_manager.spawn_enemies(&level.maze, block_size, &level.sprites, level.spawn_cell);
+    sprite_manager.spawn_items(&level.maze, block_size, &level.items, &level.sprites, level.spawn_cell);
+    level.doors = Doors::from_level(level);
+    level.exploration = Exploration::new(&level.maze);
+    player.inventory = Inventory::default();
+    
+    let (spawn_x, spawn_y) = level.spawn_cell;
+    player.pos = Vector2::new(
+        (spawn_x as f32 + 0.5) * block_size as f32,
+        (spawn_y as f32 + 0.5) * block_size as f32,
+    );
+    player.a = level.spawn_angle;
+}
+
+//Lo que se dibuja en los mapas además del jugador y los enemigos
+pub struct MapOverlays {
+    ghost: Option<(Vector2, f32)>, //Pose del fantasma de la mejor partida
+    hint: Vec<Cell>, //Próximas celdas hacia la meta, vacío si la pista está oculta
+    enemies: Vec<(Vector2, f32)>, //Posición y dirección de cada enemigo
+    map: MapMode, //Qué parte del laberinto se muestra
+}
+
+impl MapOverlays {
+    //Celda que todavía no se muestra en los mapas
+    fn hides(&self, cell: Cell, exploration: &Exploration) -> bool {
+        self.map == MapMode::Explored && !exploration.is_seen(cell)
+    }
+}
+
+//Dibujar la partida en el modo actual (también de fondo en el menú de pausa)
+fn render_game_view(
+    framebuffer: &mut Framebuffer,
+    level: &mut Level,
+    sprite_manager: &SpriteManager,
+    view_player: &Player,
+    overlays: &MapOverlays,
+    mode: &str,
+    block_size: usize,
+) {
+    if mode == "2D" {
+        //Sin render_world se recorren los mismos rayos para seguir descubriendo el mapa
+        level.exploration.look(&level.maze, &level.doors, view_player, block_size, framebuffer.width);
+        render_maze(framebuffer, &level.maze, &level.doors, &level.exploration, block_size, view_player, overlays);
+        //Los sprites solo se ven en modo 3D
+    } else {
+        render_world(framebuffer, &level.maze, &level.doors, &mut level.exploration, block_size, view_player);
+        sprite_manager.render_sprites_3d(framebuffer, view_player, block_size);
+        //Solo mostrar minimapa en modo 3D
+        if overlays.map != MapMode::Off {
+            draw_minimap(framebuffer, &level.maze, &level.doors, &level.exploration, view_player, overlays, block_size);
+        }
+    }
+}
+
+//Aplicar los ajustes guardados: volumen, campo de visión, sensibilidad y resolución
+fn apply_settings(
+    settings: &Settings,
+    window: &mut RaylibHandle,
+    framebuffer: &mut Framebuffer,
+    player: &mut Player,
+    audio_manager: &mut AudioManager,
+    input: &mut InputState,
+) {
+    audio_manager.set_volume(settings.volume);
+    player.fov = settings.fov.to_radians();
+    input.map.mouse_sensitivity = settings.mouse_sensitivity;
+    
+    let (width, height) = settings.resolution;
+    if width != framebuffer.width || height != framebuffer.height {
+        window.set_window_size(width as i32, height as i32);
+        framebuffer.resize(width, height);
+    }
+}
+
+//Colocar todo para una partida nueva; la semilla de la partida decide dónde aparecen
+//los sprites, así una repetición los vuelve a poner en el mismo lugar
+fn start_run(
+    game_manager: &mut GameManager,
+    level: &mut Level,
+    player: &mut Player,
+    previous_player: &mut Player,
+    sprite_manager: &mut SpriteManager,
+    framebuffer: &mut Framebuffer,
+    block_size: usize,
+) {
+    fastrand::seed(game_manager.run_seed);
+    reset_level(level, player, sprite_manager, framebuffer, block_size);
+    *previous_player = player.clone();
+    
+    //Fantasma de la mejor partida del nivel (si existe) y trayectoria nueva desde el spawn
+    game_manager.ghost = Ghost::load(&ghost_path(&game_manager.current_difficulty.record_key())).ok();
+    game_manager.ghost_recording.push(player);
+}
+
+//Pose del fantasma para este frame, si está activado y el nivel tiene uno
+fn ghost_pose(game_manager: &GameManager, settings: &Settings, alpha: f32) -> Option<(Vector2, f32)> {
+    if !settings.ghost {
+        return None;
+    }
+    game_manager.ghost.as_ref()?.pose(game_manager.run.ticks, alpha)
+}
+
+//Camino de la pista desde la celda del jugador, si está activada; con un mapa
+//recogido se ve el camino completo hasta la meta aunque la pista esté oculta
+fn hint_cells(level: &Level, player: &Player, show_hint: bool, block_size: usize) -> Vec<Cell> {
+    let length = if player.inventory.count(ItemKind::MapReveal) > 0 {
+        usize::MAX
+    } else if show_hint {
+        HINT_LENGTH
+    } else {
+        return Vec::new();
+    };
+    hint_path(&level.maze, world_to_cell(player.pos, block_size), length)
+}
+
+//Reiniciar el nivel sin terminar la partida: el tiempo sigue y cuenta como reinicio
+fn reset_run_level(
+    game_manager: &mut GameManager,
+    level: &mut Level,
+    player: &mut Player,
+    previous_player: &mut Player,
+    sprite_manager: &mut SpriteManager,
+    framebuffer: &mut Framebuffer,
+    block_size: usize,
+) {
+    game_manager.run.resets += 1;
+    game_manager.recording.push_reset();
+    if let Some(new_level) = load_selected_level(game_manager) {
+        *level = new_level;
+        reset_level(level, player, sprite_manager, framebuffer, block_size);
+        *previous_player = player.clone();
+    }
+}
+
+//Estado entre frames de la partida en curso que no forma parte de la simulación;
+//empezar o reiniciar una partida lo descarta entero con clear
+#[derive(Debug, Default)]
+struct PendingState {
+    accumulator: f32, //Tiempo real que todavía no se consumió en pasos de simulación
+    look: f32,        //Giro del mouse que todavía no se aplicó en un paso
+    interact: bool,   //Pulsación de Use que todavía no llegó a un paso (puede haber frames sin pasos)
+    message: Option<(String, f32)>, //Mensaje de puertas y objetos en pantalla y segundos que le quedan
+}
+
+impl PendingState {
+    fn clear(&mut self) {
+        *self = PendingState::default();
+    }
+}
+
+//Lo que pasó en un paso de simulación
+#[derive(Debug, Clone, Default)]
+struct StepOutcome {
+    moved: bool,  //El jugador se movió
+    caught: bool, //Un enemigo alcanzó al jugador
+    used: Option<UseResult>, //Qué pasó al usar una puerta o interruptor, si lo intentó
+    picked: Vec<ItemKind>,   //Objetos recogidos en este paso
+}
+
+//Un paso fijo de la simulación, igual en el juego, en las repeticiones y sin ventana.
+//Si un enemigo alcanza al jugador se aplica la regla del nivel aquí mismo, así las
+//repeticiones no necesitan guardarlo
+fn simulate_step(
+    run: &mut RunStats,
+    level: &mut Level,
+    player: &mut Player,
+    sprite_manager: &mut SpriteManager,
+    input: &PlayerInput,
+    block_size: usize,
+) -> StepOutcome {
+    let start = player.pos;
+    let moved = update_player(player, input, SIMULATION_STEP, &level.maze, &level.doors, block_size);
+    run.advance(SIMULATION_STEP, (player.pos - start).length() / block_size as f32);
+    sprite_manager.update(SIMULATION_STEP);
+    
+    //Puertas: usar la que está enfrente y animarlas
+    let used = input.interact.then(|| {
+        level.doors.use_facing(&level.maze, player.pos, player.a, PLAYER_RADIUS, &mut player.inventory, block_size)
+    });
+    level.doors.update(SIMULATION_STEP);
+    
+    //Objetos al alcance; la bonificación de tiempo se descuenta al momento
+    let picked = sprite_manager.collect_items(player.pos);
+    for &kind in &picked {
+        player.inventory.add(kind);
+        if kind == ItemKind::TimeBonus {
+            run.elapsed = (run.elapsed - TIME_BONUS).max(0.0);
+        }
+    }
+    
+    let caught = sprite_manager.update_enemies(SIMULATION_STEP, &level.maze, &level.doors, player.pos, block_size);
+    if caught {
+        run.catches += 1;
+        match level.caught {
+            CatchRule::Restart => spawn_level(level, player, sprite_manager, block_size),
+            CatchRule::Penalty(seconds) => run.elapsed += seconds,
+        }
+    }
+    
+    StepOutcome { moved, caught, used, picked }
+}
+
+//Texto para el jugador según lo que pasó al usar una puerta o interruptor
+fn use_message(result: UseResult) -> Option<&'static str> {
+    match result {
+        UseResult::Unlocked => Some("Unlocked with a key"),
+        UseResult::Locked => Some("The door is locked - find a key"),
+        UseResult::Remote => Some("This door is opened by a switch somewhere"),
+        UseResult::Blocked => Some("Something is in the way"),
+        UseResult::Switch => Some("You hear doors moving"),
+        UseResult::Nothing | UseResult::Door => None,
+    }
+}
+
+//Texto al recoger un objeto; los que pide la meta muestran cuántos van
+fn pickup_message(kind: ItemKind, inventory: &Inventory, level: &Level) -> String {
+    if let Some(needed) = level.objectives.requires(kind) {
+        return format!("{} {}/{}", kind.label(), inventory.count(kind), needed);
+    }
+    match kind {
+        ItemKind::Key => "Key picked up".to_string(),
+        ItemKind::Scroll => "Scroll picked up".to_string(),
+        ItemKind::TimeBonus => format!("Time bonus: -{} s", TIME_BONUS),
+        ItemKind::MapReveal => "Map picked up - the way to the goal is marked".to_string(),
+    }
+}
+
+//Objetos del nivel y cuántos lleva el jugador, con el total pedido si la meta los exige
+fn inventory_text(level: &Level, inventory: &Inventory) -> String {
+    ItemKind::ALL.iter()
+        .filter(|&&kind| level.item_count(kind) > 0)
+        .map(|&kind| match level.objectives.requires(kind) {
+            Some(needed) => format!("{}: {}/{}", kind.label(), inventory.count(kind), needed),
+            None => format!("{}: {}", kind.label(), inventory.count(kind)),
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+//Segundos que se muestra un mensaje de puertas o llaves
+const MESSAGE_TIME: f32 = 2.5;
+
+//Paso fijo de la simulación; el dibujo interpola entre los dos últimos pasos
+const SIMULATION_STEP: f32 = 1.0 / 60.0;
+//Máximo de tiempo simulado por frame para no quedar atrapado tras una pausa larga
+const MAX_FRAME_TIME: f32 = 0.25;
+
+//Límite de frames por segundo del dibujo
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameLimit {
+    Capped(f32),
+    Uncapped,
+    VSync,
+}
+
+//Opciones de línea de comandos:
+//  --fps N (0 = sin límite)  --vsync  --replay archivo.replay
+//y del generador para la dificultad RANDOM:
+//  --seed N  --maze-size WxH  --algorithm backtracker|prim|kruskal|eller  --loops P  --braid P
+fn apply_cli_args(game_manager: &mut GameManager, frame_limit: &mut FrameLimit, args: &[String]) -> Result<(), String> {
+    let options = &mut game_manager.random_options;
+    let mut replay_path = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--fps" => {
+                let fps: f32 = value()?.parse().map_err(|_| "invalid value for --fps".to_string())?;
+                *frame_limit = if fps > 0.0 { FrameLimit::Capped(fps) } else { FrameLimit::Uncapped };
+            },
+            "--vsync" => *frame_limit = FrameLimit::VSync,
+            "--replay" => replay_path = Some(value()?.clone()),
+            "--seed" => {
+                options.seed = value()?.parse().map_err(|_| format!("invalid seed for {}", arg))?;
+                game_manager.fixed_seed = true;
+            },
+            "--maze-size" => {
+                let size = value()?;
+                let (w, h) = size.split_once('x').ok_or(format!("invalid size '{}'", size))?;
+                options.width = w.parse().map_err(|_| format!("invalid width '{}'", w))?;
+                options.height = h.parse().map_err(|_| format!("invalid height '{}'", h))?;
+            },
+            "--algorithm" => {
+                let name = value()?;
+                options.algorithm = Algorithm::from_name(name)
+                    .ok_or(format!("unknown algorithm '{}'", name))?;
+            },
+            "--loops" => {
+                options.loops = parse_chance(value()?).ok_or("invalid chance for --loops (0 to 1)")?;
+            },
+            "--braid" => {
+                options.braid = parse_chance(value()?).ok_or("invalid chance for --braid (0 to 1)")?;
+            },
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+
+    //Empezar directamente reproduciendo la partida grabada
+    if let Some(path) = replay_path {
+        game_manager.start_replay(Replay::load(&path)?)?;
+    }
+
+    Ok(())
+}
+
 fn main() {
-    let window_width = 1300;
-    let window_height = 900;
     let block_size = 100;
     
+    //Game manager para estados
+    let mut game_manager = GameManager::new();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (headless_options, args) = match split_headless_args(&args) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("Argumento inválido: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut frame_limit = FrameLimit::Capped(60.0);
+    if let Err(e) = apply_cli_args(&mut game_manager, &mut frame_limit, &args) {
+        println!("Argumento inválido: {}", e);
+    }
+    
+    //Modo sin ventana: renderizar un frame a PNG y salir
+    if let Some(options) = headless_options {
+        if let Err(e) = run_headless(&options, &game_manager, block_size) {
+            println!("Error en renderizado sin ventana: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
+    //Ajustes del jugador (editables desde el menú SETTINGS)
+    let mut settings = Settings::load_or_default(SETTINGS_FILE);
+    let (window_width, window_height) = settings.resolution;
+    
+    //Mejores tiempos locales por nivel
+    let mut leaderboard = Leaderboard::load_or_default(LEADERBOARD_FILE);
+    if let Some(initials) = leaderboard.last_initials() {
+        game_manager.set_initials(initials);
+    }
+    
     //Inicializar sistema de audio
     let mut audio_manager = AudioManager::new().expect("No se pudo inicializar el sistema de audio");
     
     //Cargar y reproducir música de fondo
     audio_manager.play_background_music();
     
-    let (mut window, raylib_thread) = raylib::init()
-        .size(window_width, window_height)
+    let mut builder = raylib::init();
+    builder
+        .size(window_width as i32, window_height as i32)
         .title("Raycaster - Select Level")
-        .log_level(TraceLogLevel::LOG_WARNING)
-        .build();
+        .log_level(TraceLogLevel::LOG_WARNING);
+    if frame_limit == FrameLimit::VSync {
+        builder.vsync();
+    }
+    let (mut window, raylib_thread) = builder.build();
     
-    let mut framebuffer = Framebuffer::new(window_width as u32, window_height as u32);
+    let mut framebuffer = Framebuffer::new(window_width, window_height);
     framebuffer.set_background_color(Color::new(50, 50, 100, 255));
     
-    //Cargar textura de paredes
-    let wall_texture = Image::load_image("assets/img/bosque.jpg")
-        .expect("No se pudo cargar la textura bosque.jpg");
-    framebuffer.load_texture_cache(&wall_texture);
-    
-    //Cargar textura de meta (Konoha)
-    let goal_texture = Image::load_image("assets/img/konoha.jpg")
-        .expect("No se pudo cargar la textura konoha.jpg");
-    framebuffer.load_goal_texture_cache(&goal_texture);
-    
-    //Cargar textura de meta (Konoha)
-    let goal_texture = Image::load_image("assets/img/konoha.jpg")
-        .expect("No se pudo cargar la textura konoha.jpg");
-    framebuffer.load_goal_texture_cache(&goal_texture);
-    
-    //Game manager para estados
-    let mut game_manager = GameManager::new();
-    
     //Inicializar sistema de sprites
     let mut sprite_manager = SpriteManager::new();
     sprite_manager.load_sprite_textures(&mut window, &raylib_thread);
     
     //Variables del juego
-    let mut maze = load_maze("maze_easy.txt"); 
-    sprite_manager.spawn_sprites_in_maze(&maze, block_size); 
+    //El nivel real se carga al elegir una dificultad en el menú
+    let mut level = Level::from_maze("", Vec::new());
     let mut player = Player {
         pos: Vector2::new(150.0, 150.0),
         a: PI / 3.0,
         fov: PI / 3.0,
+        inventory: Inventory::default(),
     };
+    //Cargar el tema de texturas (paredes por carácter, piso y techo)
+    apply_level_textures(&level, &mut framebuffer);
     
     let mut mode = "3D"; //Iniciar en 3D por defecto
+    let mut show_hint = false; //Camino hacia la meta en el minimapa
+    
+    //Acciones asignadas a teclado, mouse y gamepad (editable desde el menú CONTROLS)
+    let mut input = InputState::new(InputMap::load_or_default(INPUT_CONFIG));
+    apply_settings(&settings, &mut window, &mut framebuffer, &mut player, &mut audio_manager, &mut input);
     
     //Variables para FPS
-    let target_fps = 15.0;
-    let frame_time = Duration::from_secs_f32(1.0 / target_fps);
     let mut fps_counter = 0;
     let mut fps_timer = Instant::now();
     let mut current_fps = 0.0;
     
+    //Simulación de paso fijo: el tiempo real se acumula y se consume en pasos iguales
+    let mut last_frame = Instant::now();
+    let mut pending = PendingState::default();
+    let mut previous_player = player.clone();
+    
+    //Con --replay la partida ya empezó: cargar su nivel antes del primer frame
+    if game_manager.state == GameState::Playing && let Some(new_level) = load_selected_level(&mut game_manager) {
+        level = new_level;
+        apply_level_textures(&level, &mut framebuffer);
+        audio_manager.set_background_music(level.music.as_deref());
+        start_run(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+    }
+    
     while !window.window_should_close() {
         let frame_start = Instant::now();
+        let frame_dt = frame_start.duration_since(last_frame).as_secs_f32().min(MAX_FRAME_TIME);
+        last_frame = frame_start;
+        let mut player_moved = false;
+        input.update(&window);
         framebuffer.clear();
         
-        //Mantener la música reproduciéndose en loop
-        audio_manager.maintain_background_music();
-        
         match game_manager.state {
             GameState::Welcome => {
                 //Manejar input del menú de bienvenida
-                handle_welcome_input(&mut game_manager, &window, &audio_manager);
+                handle_welcome_input(&mut game_manager, &input, &audio_manager);
                 
                 //Si se seleccionó un nivel, cargar el laberinto correspondiente
                 if game_manager.state == GameState::Playing {
-                    maze = load_maze(game_manager.current_difficulty.get_maze_file());
-                    sprite_manager.spawn_sprites_in_maze(&maze, block_size);
-                    player.pos = Vector2::new(150.0, 150.0);
-                    player.a = PI / 3.0;
+                    if let Some(new_level) = load_selected_level(&mut game_manager) {
+                        level = new_level;
+                        apply_level_textures(&level, &mut framebuffer);
+                        audio_manager.set_background_music(level.music.as_deref());
+                        start_run(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+                        pending.clear();
+                    }
                 }
                 
                 //Dibujar pantalla de bienvenida
@@ -458,107 +1120,294 @@ fn main() {
             },
             
             GameState::Playing => {
-                //Lógica del juego normal
-                process_events(&mut player, &window, &maze, block_size, &mut audio_manager);
+                //Lógica del juego normal; en una repetición el input sale de la grabación
+                let player_input = read_player_input(&input);
                 
-                //Verificar victoria
-                if check_victory(&player, &maze, block_size) {
-                    audio_manager.play_win_sound();
-                    game_manager.win_game();
+                //El mouse da un giro absoluto: se acumula hasta el próximo paso para que quede grabado
+                if game_manager.playback.is_none() {
+                    pending.look += player_input.look;
+                    pending.interact |= player_input.interact;
+                }
+                
+                pending.accumulator += frame_dt;
+                while pending.accumulator >= SIMULATION_STEP && game_manager.state == GameState::Playing {
+                    let event = match game_manager.playback {
+                        Some(ref mut playback) => playback.next_event(),
+                        None => {
+                            let tick_input = PlayerInput { look: pending.look, interact: pending.interact, ..player_input };
+                            pending.look = 0.0;
+                            pending.interact = false;
+                            Some(ReplayEvent::Tick(tick_input))
+                        },
+                    };
+                    
+                    let tick_input = match event {
+                        Some(ReplayEvent::Tick(tick_input)) => tick_input,
+                        Some(ReplayEvent::Reset) => {
+                            reset_run_level(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+                            continue;
+                        },
+                        None => {
+                            let ticks = game_manager.playback.as_ref().map_or(0, |p| p.ticks_played());
+                            game_manager.reset_to_welcome();
+                            game_manager.load_error = Some(format!("Replay ended after {} ticks without reaching the goal", ticks));
+                            break;
+                        },
+                    };
+                    
+                    game_manager.recording.push_tick(tick_input);
+                    previous_player = player.clone();
+                    let outcome = simulate_step(&mut game_manager.run, &mut level, &mut player, &mut sprite_manager, &tick_input, block_size);
+                    player_moved |= outcome.moved;
+                    game_manager.ghost_recording.push(&player);
+                    //El giro del mouse no se interpola, ya se estaba mostrando antes del paso
+                    previous_player.a += tick_input.look;
+                    pending.accumulator -= SIMULATION_STEP;
+                    
+                    if let Some(text) = outcome.used.and_then(use_message) {
+                        pending.message = Some((text.to_string(), MESSAGE_TIME));
+                    }
+                    if outcome.used.is_some_and(|used| matches!(used, UseResult::Door | UseResult::Unlocked | UseResult::Switch)) {
+                        audio_manager.play_menu_sound();
+                    }
+                    for &kind in &outcome.picked {
+                        audio_manager.play_start_sound();
+                        pending.message = Some((pickup_message(kind, &player.inventory, &level), MESSAGE_TIME));
+                    }
+                    
+                    if outcome.caught {
+                        audio_manager.play_up_down_sound();
+                        //Si volvió al spawn no hay que interpolar desde donde lo atraparon
+                        if level.caught == CatchRule::Restart {
+                            previous_player = player.clone();
+                        }
+                    }
+                    
+                    //Verificar objetivos en cada paso para que el tiempo no dependa de los FPS
+                    let state = level.objectives.check(&player, &game_manager.run, &level.maze, block_size);
+                    
+                    //En la meta con objetivos pendientes: avisar cuál falta
+                    if state == ObjectiveState::Running && on_goal(&player, &level.maze, block_size) &&
+                        let Some(objective) = level.objectives.first_pending(&player, &game_manager.run, &level.maze, block_size) {
+                        pending.message = Some((format!("Not yet: {}", objective.describe(&player, &game_manager.run)), MESSAGE_TIME));
+                    }
+                    
+                    if let ObjectiveState::Lost(reason) = state {
+                        audio_manager.play_up_down_sound();
+                        game_manager.lose_game(reason);
+                        match game_manager.playback {
+                            Some(_) => println!("Repetición terminada en derrota: {}", reason.reason()),
+                            None => save_last_run(&game_manager.recording),
+                        }
+                    }
+                    
+                    if state == ObjectiveState::Won {
+                        game_manager.run.explored = level.exploration.percent(&level.maze);
+                        audio_manager.play_win_sound();
+                        game_manager.win_game();
+                        match game_manager.playback {
+                            Some(ref playback) => println!(
+                                "Repetición terminada en {} (tiempo grabado: {})",
+                                format_time(game_manager.run.elapsed),
+                                playback.replay.time.map_or("-".to_string(), format_time),
+                            ),
+                            None => {
+                                game_manager.recording.time = Some(game_manager.run.elapsed);
+                                save_last_run(&game_manager.recording);
+                                
+                                //La mejor partida del nivel pasa a ser el fantasma
+                                game_manager.ghost_recording.time = game_manager.run.elapsed;
+                                let level_key = game_manager.current_difficulty.record_key();
+                                if save_if_best(&level_key, &game_manager.ghost_recording, game_manager.ghost.as_ref()) {
+                                    println!("Nuevo fantasma guardado en {}", ghost_path(&level_key));
+                                }
+                            },
+                        }
+                    }
                 }
                 
-                //Controles de teclado adicionales
-                if window.is_key_pressed(KeyboardKey::KEY_Q) {
-                    // Q - Ir al menú principal
+                //Pausar la partida sin perder el progreso
+                if game_manager.state == GameState::Playing && input.is_pressed(Action::Menu) {
                     audio_manager.play_menu_sound();
-                    game_manager.reset_to_welcome();
+                    game_manager.pause();
                 }
                 
-                if window.is_key_pressed(KeyboardKey::KEY_R) {
-                    // R - Reset nivel actual
+                //Reset nivel actual; el tiempo de la partida sigue corriendo
+                if game_manager.state == GameState::Playing && game_manager.playback.is_none() && input.is_pressed(Action::Reset) {
                     audio_manager.play_start_sound();
-                    maze = load_maze(game_manager.current_difficulty.get_maze_file());
-                    sprite_manager.spawn_sprites_in_maze(&maze, block_size);
-                    player.pos = Vector2::new(150.0, 150.0);
-                    player.a = PI / 3.0;
-                    framebuffer.set_background_color(Color::new(50, 50, 100, 255));
+                    reset_run_level(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
                 }
                 
-                //Controles adicionales con gamepad
-                if window.is_gamepad_available(0) {
-                    // Botón Start/Options - Ir al menú principal
-                    if window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT) {
-                        audio_manager.play_menu_sound();
-                        game_manager.reset_to_welcome();
-                    }
-                    
-                    //Botón Select/Share - Reset nivel actual
-                    if window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT) {
-                        audio_manager.play_start_sound();
-                        maze = load_maze(game_manager.current_difficulty.get_maze_file());
-                        sprite_manager.spawn_sprites_in_maze(&maze, block_size);
-                        player.pos = Vector2::new(150.0, 150.0);
-                        player.a = PI / 3.0;
-                        framebuffer.set_background_color(Color::new(50, 50, 100, 255));
-                    }
+                //Cambiar modo 2D/3D; sin mapa solo queda la vista 3D
+                if input.is_pressed(Action::ToggleMap) && settings.map != MapMode::Off {
+                    mode = if mode == "3D" { "2D" } else { "3D" }; 
+                }
+                if settings.map == MapMode::Off {
+                    mode = "3D";
                 }
                 
-                //Cambiar modo con la tecla M o gamepad
-                if window.is_key_pressed(KeyboardKey::KEY_M) || check_gamepad_mode_change(&window) {
-                    mode = if mode == "3D" { "2D" } else { "3D" }; 
+                //Mostrar u ocultar la pista hacia la meta
+                if input.is_pressed(Action::ToggleHint) {
+                    show_hint = !show_hint;
                 }
                 
-                //Actualizar sprites
-                sprite_manager.update(1.0 / 60.0); 
+                //Dibujar en la posición interpolada entre los dos últimos pasos, con el giro pendiente
+                let mut view_player = interpolate_player(&previous_player, &player, pending.accumulator / SIMULATION_STEP);
+                view_player.a += pending.look;
+                let overlays = MapOverlays {
+                    ghost: ghost_pose(&game_manager, &settings, pending.accumulator / SIMULATION_STEP),
+                    hint: hint_cells(&level, &view_player, show_hint, block_size),
+                    enemies: sprite_manager.enemy_markers(),
+                    map: settings.map,
+                };
+                sprite_manager.set_ghost(overlays.ghost.map(|(pos, _)| pos));
                 
                 //Dibujar juego según el modo
-                if mode == "2D" {
-                    render_maze(&mut framebuffer, &maze, block_size, &player);
-                    //Los sprites solo se ven en modo 3D
-                } else {
-                    render_world(&mut framebuffer, &maze, block_size, &player);
-                    sprite_manager.render_sprites_3d(&mut framebuffer, &player, &maze, block_size);
-                    //Solo mostrar minimapa en modo 3D
-                    draw_minimap(&mut framebuffer, &maze, &player, block_size);
-                }
+                render_game_view(&mut framebuffer, &mut level, &sprite_manager, &view_player, &overlays, mode, block_size);
                 
                 //Mostrar información
                 let fps_text = format!("FPS: {:.1}", current_fps);
                 framebuffer.draw_text(&fps_text, 10, 10, 16, Color::WHITE);
                 
-                let mode_text = format!("Mode: {} (Press M or Triangle to change)", mode);
+                let mode_text = format!("Mode: {} (Press {} to change)", mode, input.map.key_name(Action::ToggleMap));
                 framebuffer.draw_text(&mode_text, 10, 30, 16, Color::WHITE);
                 
                 //Mover los controles a la esquina inferior izquierda para evitar superposición
-                let controls_text = "Q=Menu | R=Reset | Options=Menu | Share=Reset";
+                let controls_text = &format!(
+                    "{}=Use | {}=Pause | {}=Reset | {}=Hint",
+                    input.map.key_name(Action::Use), input.map.key_name(Action::Menu), input.map.key_name(Action::Reset), input.map.key_name(Action::ToggleHint),
+                );
                 let controls_y = framebuffer.height.saturating_sub(25);
                 framebuffer.draw_text(&controls_text, 10, controls_y, 14, Color::LIGHTGRAY);
                 
-                let level_text = format!("Level: {}", game_manager.current_difficulty.get_name());
+                let level_text = match game_manager.current_difficulty {
+                    Difficulty::Random(ref options) => format!("Level: RANDOM (seed {})", options.seed),
+                    _ => format!("Level: {}", game_manager.current_difficulty.get_name()),
+                };
                 framebuffer.draw_text(&level_text, 10, 50, 16, Color::WHITE);
                 
                 let gamepad_text = get_gamepad_info(&window);
                 framebuffer.draw_text(&gamepad_text, 10, 70, 16, Color::WHITE);
+                
+                let mut run_text = format!("Time: {}  Resets: {}", format_time(game_manager.run.elapsed), game_manager.run.resets);
+                if !level.sprites.routes.is_empty() || level.sprites.enemies > 0 {
+                    run_text.push_str(&format!("  Caught: {}", game_manager.run.catches));
+                }
+                framebuffer.draw_text(&run_text, 10, 90, 16, Color::WHITE);
+                
+                //Inventario, solo con los objetos que tiene el nivel
+                let mut next_line = 110;
+                let items_text = inventory_text(&level, &player.inventory);
+                if !items_text.is_empty() {
+                    framebuffer.draw_text(&items_text, 10, next_line, 16, Color::WHITE);
+                    next_line += 20;
+                }
+                
+                //Objetivos en curso: cumplidos en verde y condiciones de derrota en rojo
+                for objective in &level.objectives.goals {
+                    let done = objective.is_done(&player, &game_manager.run, &level.maze, block_size);
+                    let (mark, color) = if done { ("[x]", Color::GREEN) } else { ("[ ]", Color::WHITE) };
+                    let text = format!("{} {}", mark, objective.describe(&player, &game_manager.run));
+                    framebuffer.draw_text(&text, 10, next_line, 16, color);
+                    next_line += 20;
+                }
+                for fail in &level.objectives.fails {
+                    framebuffer.draw_text(&format!(" !  {}", fail.describe(&game_manager.run)), 10, next_line, 16, Color::new(255, 110, 110, 255));
+                    next_line += 20;
+                }
+                
+                //Mensaje de puertas y objetos centrado abajo hasta que se acabe su tiempo
+                if let Some((text, remaining)) = pending.message.take() {
+                    let text_x = (framebuffer.width / 2).saturating_sub(text.len() as u32 * 5).max(10);
+                    framebuffer.draw_text(&text, text_x, framebuffer.height.saturating_sub(60), 20, Color::YELLOW);
+                    pending.message = (remaining > frame_dt).then_some((text, remaining - frame_dt));
+                }
+                
+                if let Some(ref playback) = game_manager.playback {
+                    let replay_text = format!("REPLAY  tick {}/{}", playback.ticks_played(), playback.total_ticks());
+                    framebuffer.draw_text(&replay_text, 10, next_line, 16, Color::YELLOW);
+                }
+            },
+            
+            GameState::Paused => {
+                //La simulación queda detenida; se dibuja la escena tal como quedó
+                let view_player = interpolate_player(&previous_player, &player, pending.accumulator / SIMULATION_STEP);
+                let overlays = MapOverlays {
+                    ghost: ghost_pose(&game_manager, &settings, pending.accumulator / SIMULATION_STEP),
+                    hint: hint_cells(&level, &view_player, show_hint, block_size),
+                    enemies: sprite_manager.enemy_markers(),
+                    map: settings.map,
+                };
+                sprite_manager.set_ghost(overlays.ghost.map(|(pos, _)| pos));
+                render_game_view(&mut framebuffer, &mut level, &sprite_manager, &view_player, &overlays, mode, block_size);
+                draw_pause_menu(&mut framebuffer, &game_manager);
+                
+                let action = handle_pause_input(&mut game_manager, &input, &audio_manager);
+                if action == PauseAction::RestartLevel {
+                    if game_manager.playback.is_some() {
+                        //Una repetición vuelve a empezar desde el primer paso
+                        game_manager.restart_run();
+                        if let Some(new_level) = load_selected_level(&mut game_manager) {
+                            level = new_level;
+                            start_run(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+                        }
+                    } else {
+                        reset_run_level(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+                    }
+                    pending.clear();
+                }
+            },
+            
+            GameState::Settings => {
+                if handle_settings_input(&mut game_manager, &mut settings, &input, &audio_manager) {
+                    apply_settings(&settings, &mut window, &mut framebuffer, &mut player, &mut audio_manager, &mut input);
+                    previous_player.fov = player.fov;
+                }
+                draw_settings_screen(&mut framebuffer, &game_manager, &settings);
+            },
+            
+            GameState::Controls => {
+                handle_controls_input(&mut game_manager, &mut input, &mut window, &audio_manager);
+                draw_controls_screen(&mut framebuffer, &game_manager, &input.map);
             },
             
             GameState::Victory => {
                 //Renderizar pantalla de victoria con imagen de fondo
-                render_victory_screen(&mut framebuffer);
+                render_victory_screen(&mut framebuffer, &game_manager, &leaderboard, &input.map);
                 
-                //Manejar input de victoria
-                let action = handle_victory_input(&mut game_manager, &window, &audio_manager);
+                //Manejar input de victoria (iniciales y luego menú o reinicio)
+                let action = handle_victory_input(&mut game_manager, &input, &mut window, &mut leaderboard, &audio_manager);
                 match action {
                     VictoryAction::RestartLevel => {
-                        //Reiniciar el mismo nivel
-                        maze = load_maze(game_manager.current_difficulty.get_maze_file());
-                        sprite_manager.spawn_sprites_in_maze(&maze, block_size);
-                        player.pos = Vector2::new(150.0, 150.0);
-                        player.a = PI / 3.0;
-                        framebuffer.set_background_color(Color::new(50, 50, 100, 255));
+                        //Reiniciar el mismo nivel como una partida nueva
+                        if let Some(new_level) = load_selected_level(&mut game_manager) {
+                            level = new_level;
+                            start_run(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+                            pending.clear();
+                        }
                     },
                     _ => {} //BackToMenu y None se manejan automáticamente
                 }
-            }
+            },
+            
+            GameState::Defeat => {
+                //La escena queda congelada donde se perdió, con el resultado encima
+                let overlays = MapOverlays {
+                    ghost: None,
+                    hint: Vec::new(),
+                    enemies: sprite_manager.enemy_markers(),
+                    map: settings.map,
+                };
+                render_game_view(&mut framebuffer, &mut level, &sprite_manager, &player, &overlays, mode, block_size);
+                draw_defeat_screen(&mut framebuffer, &game_manager, &input.map);
+                
+                if handle_defeat_input(&mut game_manager, &input, &audio_manager) == VictoryAction::RestartLevel &&
+                    let Some(new_level) = load_selected_level(&mut game_manager) {
+                    level = new_level;
+                    start_run(&mut game_manager, &mut level, &mut player, &mut previous_player, &mut sprite_manager, &mut framebuffer, block_size);
+                    pending.clear();
+                }
+            },
         }
         
         //Calcular FPS
@@ -569,13 +1418,19 @@ fn main() {
             fps_timer = Instant::now();
         }
         
+        //Sonido de pasos y música con el tiempo real del frame
+        audio_manager.update(frame_dt, player_moved);
+        
         //Intercambiar buffers
         framebuffer.swap_buffers(&mut window, &raylib_thread);
         
-        //Control de FPS - mantener 15 FPS estables
-        let frame_duration = frame_start.elapsed();
-        if frame_duration < frame_time {
-            thread::sleep(frame_time - frame_duration);
+        //Control de FPS según el límite configurado
+        if let FrameLimit::Capped(target_fps) = frame_limit {
+            let frame_time = Duration::from_secs_f32(1.0 / target_fps);
+            let frame_duration = frame_start.elapsed();
+            if frame_duration < frame_time {
+                thread::sleep(frame_time - frame_duration);
+            }
         }
     }
 }
\ No newline at end of file
@@ -1,14 +1,8 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::level::{load_level, LevelError};
 
 pub type Maze = Vec<Vec<char>>;
 
-pub fn load_maze(filename: &str) -> Maze {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(file);
-
-    reader
-        .lines()
-        .map(|line| line.unwrap().chars().collect())
-        .collect()
-}
\ No newline at end of file
+//Solo la cuadrícula del nivel, con o sin encabezado
+pub fn load_maze(filename: &str) -> Result<Maze, LevelError> {
+    load_level(filename).map(|level| level.maze)
+}
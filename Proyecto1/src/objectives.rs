@@ -0,0 +1,197 @@
+use crate::maze::Maze;
+use crate::items::ItemKind;
+use crate::pathfinding::{Cell, world_to_cell};
+use crate::player::{Player, on_goal};
+use crate::stats::{RunStats, format_time};
+use crate::SIMULATION_STEP;
+
+//Condición para ganar; el nivel se gana cuando todas se cumplen a la vez
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Objective {
+    ReachGoal,              //Pisar una celda 'g'
+    ReachCell(Cell),        //Pisar una celda concreta
+    Collect(ItemKind, u32), //Llevar al menos N objetos de un tipo
+    Survive(f32),           //Seguir jugando N segundos (sin contar penalizaciones ni bonificaciones)
+}
+
+//Condición para perder; basta con que se cumpla una
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailCondition {
+    TimeLimit(f32), //El tiempo de la partida llega al límite
+    Caught(u32),    //Los enemigos lo alcanzan N veces
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectiveState {
+    Running,
+    Won,
+    Lost(FailCondition),
+}
+
+//Segundos jugados de verdad, contando pasos de simulación
+fn played_time(run: &RunStats) -> f32 {
+    run.ticks as f32 * SIMULATION_STEP
+}
+
+impl Objective {
+    pub fn is_done(&self, player: &Player, run: &RunStats, maze: &Maze, block_size: usize) -> bool {
+        match *self {
+            Objective::ReachGoal => on_goal(player, maze, block_size),
+            Objective::ReachCell(cell) => world_to_cell(player.pos, block_size) == cell,
+            Objective::Collect(kind, count) => player.inventory.count(kind) >= count,
+            Objective::Survive(seconds) => played_time(run) >= seconds,
+        }
+    }
+
+    //Objetivos de posición: si el nivel no declara ninguno se agrega ReachGoal
+    pub fn is_destination(&self) -> bool {
+        matches!(self, Objective::ReachGoal | Objective::ReachCell(_) | Objective::Survive(_))
+    }
+
+    //Texto del HUD con el progreso
+    pub fn describe(&self, player: &Player, run: &RunStats) -> String {
+        match *self {
+            Objective::ReachGoal => "Reach the goal".to_string(),
+            Objective::ReachCell((x, y)) => format!("Reach cell {} {}", x, y),
+            Objective::Collect(kind, count) => {
+                format!("Collect {} {}/{}", kind.label().to_lowercase(), player.inventory.count(kind).min(count), count)
+            },
+            Objective::Survive(seconds) => {
+                format!("Survive {} / {}", format_time(played_time(run).min(seconds)), format_time(seconds))
+            },
+        }
+    }
+}
+
+impl FailCondition {
+    pub fn is_met(&self, run: &RunStats) -> bool {
+        match *self {
+            FailCondition::TimeLimit(seconds) => run.elapsed >= seconds,
+            FailCondition::Caught(count) => run.catches >= count,
+        }
+    }
+
+    pub fn describe(&self, run: &RunStats) -> String {
+        match *self {
+            FailCondition::TimeLimit(seconds) => format!("Time left {}", format_time((seconds - run.elapsed).max(0.0))),
+            FailCondition::Caught(count) => format!("Caught {}/{}", run.catches.min(count), count),
+        }
+    }
+
+    //Motivo en la pantalla de derrota
+    pub fn reason(&self) -> &str {
+        match self {
+            FailCondition::TimeLimit(_) => "Time is up!",
+            FailCondition::Caught(_) => "The enemies caught you!",
+        }
+    }
+}
+
+//Objetivos y condiciones de derrota de un nivel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Objectives {
+    pub goals: Vec<Objective>,
+    pub fails: Vec<FailCondition>,
+}
+
+impl Objectives {
+    //Se revisa en cada paso; perder tiene prioridad si ambas cosas pasan en el mismo paso
+    pub fn check(&self, player: &Player, run: &RunStats, maze: &Maze, block_size: usize) -> ObjectiveState {
+        if let Some(&fail) = self.fails.iter().find(|fail| fail.is_met(run)) {
+            return ObjectiveState::Lost(fail);
+        }
+        if self.goals.iter().all(|goal| goal.is_done(player, run, maze, block_size)) {
+            return ObjectiveState::Won;
+        }
+        ObjectiveState::Running
+    }
+
+    //Primer objetivo sin cumplir
+    pub fn first_pending(&self, player: &Player, run: &RunStats, maze: &Maze, block_size: usize) -> Option<&Objective> {
+        self.goals.iter().find(|goal| !goal.is_done(player, run, maze, block_size))
+    }
+
+    pub fn requires(&self, kind: ItemKind) -> Option<u32> {
+        self.goals.iter().find_map(|goal| match *goal {
+            Objective::Collect(k, count) if k == kind => Some(count),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raylib::prelude::*;
+    use std::f32::consts::PI;
+    use crate::items::Inventory;
+    use crate::level::parse_level;
+
+    const BLOCK_SIZE: usize = 100;
+
+    fn maze() -> Maze {
+        ["+----+", "|   g|", "+----+"].iter().map(|row| row.chars().collect()).collect()
+    }
+
+    //Jugador en el centro de una celda
+    fn player_at((x, y): Cell) -> Player {
+        Player {
+            pos: Vector2::new((x as f32 + 0.5) * BLOCK_SIZE as f32, (y as f32 + 0.5) * BLOCK_SIZE as f32),
+            a: PI / 3.0,
+            fov: PI / 3.0,
+            inventory: Inventory::default(),
+        }
+    }
+
+    #[test]
+    fn losing_beats_winning() {
+        let objectives = Objectives {
+            goals: vec![Objective::ReachGoal],
+            fails: vec![FailCondition::TimeLimit(30.0)],
+        };
+        let run = RunStats { elapsed: 30.0, ..RunStats::default() };
+
+        //Llega a la meta en el mismo paso en que se acaba el tiempo
+        let state = objectives.check(&player_at((4, 1)), &run, &maze(), BLOCK_SIZE);
+        assert_eq!(state, ObjectiveState::Lost(FailCondition::TimeLimit(30.0)));
+
+        let run = RunStats { elapsed: 29.0, ..run };
+        assert_eq!(objectives.check(&player_at((4, 1)), &run, &maze(), BLOCK_SIZE), ObjectiveState::Won);
+        assert_eq!(objectives.check(&player_at((1, 1)), &run, &maze(), BLOCK_SIZE), ObjectiveState::Running);
+    }
+
+    #[test]
+    fn survive_counts_ticks() {
+        let objectives = Objectives { goals: vec![Objective::Survive(10.0)], fails: Vec::new() };
+        let player = player_at((1, 1));
+        let ticks = (10.0 / SIMULATION_STEP).round() as usize;
+
+        //Las bonificaciones bajan el tiempo de la partida pero no lo jugado
+        let run = RunStats { elapsed: -5.0, ticks, ..RunStats::default() };
+        assert_eq!(objectives.check(&player, &run, &maze(), BLOCK_SIZE), ObjectiveState::Won);
+
+        //Y las penalizaciones lo suben sin adelantar el objetivo
+        let run = RunStats { elapsed: 60.0, ticks: ticks - 1, ..RunStats::default() };
+        assert_eq!(objectives.check(&player, &run, &maze(), BLOCK_SIZE), ObjectiveState::Running);
+    }
+
+    #[test]
+    fn require_adds_a_collect_objective() {
+        let text = "#level 1\nrequire = scroll 2\n[grid]\n+----+\n|pp g|\n+----+";
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let level = parse_level("test.txt", &lines).unwrap();
+        assert_eq!(level.objectives.goals, vec![Objective::ReachGoal, Objective::Collect(ItemKind::Scroll, 2)]);
+        assert_eq!(level.objectives.requires(ItemKind::Scroll), Some(2));
+        assert_eq!(level.objectives.requires(ItemKind::Key), None);
+
+        //En la meta sin los pergaminos todavía no se gana
+        let mut player = player_at((4, 1));
+        let run = RunStats::default();
+        player.inventory.add(ItemKind::Scroll);
+        assert_eq!(level.objectives.check(&player, &run, &level.maze, BLOCK_SIZE), ObjectiveState::Running);
+        assert_eq!(level.objectives.first_pending(&player, &run, &level.maze, BLOCK_SIZE), Some(&Objective::Collect(ItemKind::Scroll, 2)));
+
+        player.inventory.add(ItemKind::Scroll);
+        assert_eq!(level.objectives.check(&player, &run, &level.maze, BLOCK_SIZE), ObjectiveState::Won);
+    }
+}
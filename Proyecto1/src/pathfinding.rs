@@ -0,0 +1,286 @@
+use raylib::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use crate::maze::Maze;
+use crate::level::{is_walkable, is_passable};
+
+//Celda de la cuadrícula como (columna, fila)
+pub type Cell = (usize, usize);
+
+//Celda del laberinto que contiene una posición del mundo
+pub fn world_to_cell(position: Vector2, block_size: usize) -> Cell {
+    (
+        (position.x.max(0.0) / block_size as f32) as usize,
+        (position.y.max(0.0) / block_size as f32) as usize,
+    )
+}
+
+pub fn cell_center(cell: Cell, block_size: usize) -> Vector2 {
+    Vector2::new(
+        (cell.0 as f32 + 0.5) * block_size as f32,
+        (cell.1 as f32 + 0.5) * block_size as f32,
+    )
+}
+
+//Camino en coordenadas del mundo, por el centro de cada celda
+pub fn path_to_world(path: &[Cell], block_size: usize) -> Vec<Vector2> {
+    path.iter().map(|&cell| cell_center(cell, block_size)).collect()
+}
+
+//Costo por defecto: cualquier celda caminable cuesta 1, las paredes no se pueden pasar
+pub fn walkable_cost(maze: &Maze) -> impl Fn(Cell) -> Option<u32> + '_ {
+    move |(x, y)| is_walkable(maze[y][x]).then_some(1)
+}
+
+//Vecinos ortogonales dentro de la cuadrícula, siempre en el mismo orden
+fn neighbors(maze: &Maze, (x, y): Cell) -> impl Iterator<Item = Cell> + '_ {
+    [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ]
+    .into_iter()
+    .filter(|&(nx, ny)| ny < maze.len() && nx < maze[ny].len())
+}
+
+//Pasos por celdas caminables desde el origen más cercano a cada celda
+pub struct DistanceField {
+    distances: Vec<Vec<Option<usize>>>,
+}
+
+impl DistanceField {
+    pub fn get(&self, (x, y): Cell) -> Option<usize> {
+        *self.distances.get(y)?.get(x)?
+    }
+
+    //Celdas alcanzadas con su distancia
+    pub fn cells(&self) -> impl Iterator<Item = (Cell, usize)> + '_ {
+        self.distances.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().filter_map(move |(x, distance)| distance.map(|d| ((x, y), d)))
+        })
+    }
+
+    //Camino desde una celda hasta el origen más cercano bajando por las distancias
+    pub fn path_from(&self, maze: &Maze, from: Cell) -> Option<Vec<Cell>> {
+        let mut distance = self.get(from)?;
+        let mut path = vec![from];
+        let mut cell = from;
+
+        while distance > 0 {
+            cell = neighbors(maze, cell).find(|&next| self.get(next) == Some(distance - 1))?;
+            path.push(cell);
+            distance -= 1;
+        }
+
+        Some(path)
+    }
+}
+
+//Búsqueda en anchura desde una o varias celdas de origen (por ejemplo todas las metas)
+//por las celdas que acepte passable
+pub fn distance_field(maze: &Maze, sources: &[Cell], passable: fn(char) -> bool) -> DistanceField {
+    let mut field = DistanceField {
+        distances: maze.iter().map(|row| vec![None; row.len()]).collect(),
+    };
+    let mut queue = VecDeque::new();
+
+    for &(x, y) in sources {
+        if let Some(slot) = field.distances.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *slot = Some(0);
+            queue.push_back((x, y));
+        }
+    }
+
+    while let Some(cell) = queue.pop_front() {
+        let distance = field.get(cell).unwrap_or(0);
+        for (nx, ny) in neighbors(maze, cell) {
+            if field.distances[ny][nx].is_none() && passable(maze[ny][nx]) {
+                field.distances[ny][nx] = Some(distance + 1);
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    field
+}
+
+//Camino más corto por celdas caminables, incluyendo inicio y destino
+pub fn find_path(maze: &Maze, from: Cell, to: Cell) -> Option<Vec<Cell>> {
+    distance_field(maze, &[to], is_walkable).path_from(maze, from)
+}
+
+//A* con costo por celda (el costo de entrar en ella, None si no se puede pasar).
+//La heurística es la distancia Manhattan, así que los costos deben ser al menos 1
+pub fn astar<F>(maze: &Maze, from: Cell, to: Cell, cost: F) -> Option<Vec<Cell>>
+where
+    F: Fn(Cell) -> Option<u32>,
+{
+    let height = maze.len();
+    let width = maze.iter().map(|row| row.len()).max().unwrap_or(0);
+    if from.1 >= height || from.0 >= maze[from.1].len() {
+        return None;
+    }
+
+    let index = |(x, y): Cell| y * width + x;
+    let heuristic = |(x, y): Cell| (x.abs_diff(to.0) + y.abs_diff(to.1)) as u32;
+    let mut best = vec![u32::MAX; width * height];
+    let mut came_from: Vec<Option<Cell>> = vec![None; width * height];
+    let mut open = BinaryHeap::new();
+
+    best[index(from)] = 0;
+    open.push(Reverse((heuristic(from), 0, from)));
+
+    while let Some(Reverse((_, spent, cell))) = open.pop() {
+        if cell == to {
+            //Reconstruir el camino hacia atrás
+            let mut path = vec![cell];
+            let mut current = cell;
+            while let Some(previous) = came_from[index(current)] {
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if spent > best[index(cell)] {
+            continue;
+        }
+
+        for next in neighbors(maze, cell) {
+            let Some(step) = cost(next) else {
+                continue;
+            };
+            let total = spent + step;
+            if total < best[index(next)] {
+                best[index(next)] = total;
+                came_from[index(next)] = Some(cell);
+                open.push(Reverse((total + heuristic(next), total, next)));
+            }
+        }
+    }
+
+    None
+}
+
+//Todas las metas 'g' de la cuadrícula
+pub fn goal_cells(maze: &Maze) -> Vec<Cell> {
+    let mut goals = Vec::new();
+    for (y, row) in maze.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            if cell == 'g' {
+                goals.push((x, y));
+            }
+        }
+    }
+    goals
+}
+
+//Primeras celdas del camino más corto hacia la meta más cercana (sin contar la actual);
+//el camino puede pasar por puertas
+pub fn hint_path(maze: &Maze, from: Cell, length: usize) -> Vec<Cell> {
+    let mut path = distance_field(maze, &goal_cells(maze), is_passable).path_from(maze, from).unwrap_or_default();
+    path.truncate(length.saturating_add(1));
+    path.into_iter().skip(1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze(rows: &[&str]) -> Maze {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    //Rodeo obligado: de (1, 1) a la meta (5, 3) hay 6 pasos
+    fn corridor() -> Maze {
+        maze(&[
+            "+-----+",
+            "|     |",
+            "| +-+ |",
+            "|    g|",
+            "+-----+",
+        ])
+    }
+
+    fn walled() -> Maze {
+        maze(&[
+            "+-----+",
+            "| | g |",
+            "+-----+",
+        ])
+    }
+
+    //Cada paso del camino es a una celda vecina
+    fn assert_connected(path: &[Cell]) {
+        for pair in path.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "{:?} -> {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn shortest_path_length() {
+        let maze = corridor();
+        let field = distance_field(&maze, &[(5, 3)], is_walkable);
+        assert_eq!(field.get((5, 3)), Some(0));
+        assert_eq!(field.get((1, 1)), Some(6));
+        assert_eq!(field.get((0, 0)), None);
+
+        let path = find_path(&maze, (1, 1), (5, 3)).expect("hay camino");
+        assert_eq!(path.len(), 7);
+        assert_eq!(path.first(), Some(&(1, 1)));
+        assert_eq!(path.last(), Some(&(5, 3)));
+        assert_connected(&path);
+
+        let path = astar(&maze, (1, 1), (5, 3), walkable_cost(&maze)).expect("hay camino");
+        assert_eq!(path.len(), 7);
+        assert_connected(&path);
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let maze = walled();
+        let field = distance_field(&maze, &[(4, 1)], is_walkable);
+        assert_eq!(field.get((1, 1)), None);
+        assert_eq!(field.path_from(&maze, (1, 1)), None);
+        assert_eq!(find_path(&maze, (1, 1), (4, 1)), None);
+        assert_eq!(astar(&maze, (1, 1), (4, 1), walkable_cost(&maze)), None);
+        assert!(hint_path(&maze, (1, 1), 5).is_empty());
+    }
+
+    #[test]
+    fn astar_avoids_expensive_cells() {
+        let maze = maze(&[
+            "+-----+",
+            "|     |",
+            "|     |",
+            "+-----+",
+        ]);
+        //Sin costos extra va en línea recta
+        let straight = astar(&maze, (1, 1), (5, 1), walkable_cost(&maze)).expect("hay camino");
+        assert_eq!(straight, vec![(1, 1), (2, 1), (3, 1), (4, 1), (5, 1)]);
+
+        //Con la fila de arriba cara conviene bajar, cruzar y volver a subir
+        let walkable = walkable_cost(&maze);
+        let cost = |(x, y): Cell| walkable((x, y)).map(|c| if y == 1 && (2..=4).contains(&x) { 10 } else { c });
+        let path = astar(&maze, (1, 1), (5, 1), cost).expect("hay camino");
+        assert_connected(&path);
+        assert_eq!(path.len(), 7);
+        let total: u32 = path.iter().skip(1).map(|&cell| cost(cell).unwrap()).sum();
+        assert_eq!(total, 6);
+    }
+
+    #[test]
+    fn hint_path_truncation() {
+        let maze = corridor();
+        let full = find_path(&maze, (1, 1), (5, 3)).expect("hay camino");
+
+        //Sin la celda actual y como mucho length celdas
+        assert_eq!(hint_path(&maze, (1, 1), 3), full[1..4].to_vec());
+        assert_eq!(hint_path(&maze, (1, 1), 100), full[1..].to_vec());
+        assert!(hint_path(&maze, (1, 1), 0).is_empty());
+        //Parado en la meta no hay pista
+        assert!(hint_path(&maze, (5, 3), 3).is_empty());
+    }
+}
@@ -1,12 +1,17 @@
 use raylib::prelude::*;
 use std::f32::consts::PI;
 use crate::maze::Maze;
-use crate::audio::AudioManager;
+use crate::collision::move_circle;
+use crate::doors::Doors;
+use crate::items::Inventory;
+use crate::input::{Action, InputState};
 
+#[derive(Debug, Clone)]
 pub struct Player {
     pub pos: Vector2,
     pub a: f32,
     pub fov: f32,
+    pub inventory: Inventory, //Objetos recogidos en el nivel actual
 }
 
 pub fn get_gamepad_info(rl: &RaylibHandle) -> String {
@@ -20,29 +25,8 @@ pub fn get_gamepad_info(rl: &RaylibHandle) -> String {
     }
 }
 
-pub fn check_gamepad_mode_change(rl: &RaylibHandle) -> bool {
-    if rl.is_gamepad_available(0) {
-        //Usar el botón Triangle (Y en Xbox, Triangle en PS) para cambiar modo
-        rl.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP)
-    } else {
-        false
-    }
-}
-
-fn is_valid_position(pos: Vector2, maze: &Maze, block_size: usize) -> bool {
-    let x = pos.x as usize / block_size;
-    let y = pos.y as usize / block_size;
-    
-    //Verificar límites del laberinto
-    if y >= maze.len() || x >= maze[0].len() {
-        return false;
-    }
-    
-    //Verificar si la posición es un espacio vacío o la meta
-    maze[y][x] == ' ' || maze[y][x] == 'g'
-}
-
-pub fn check_victory(player: &Player, maze: &Maze, block_size: usize) -> bool {
+//Si el jugador está parado en una celda meta 'g'
+pub fn on_goal(player: &Player, maze: &Maze, block_size: usize) -> bool {
     let x = player.pos.x as usize / block_size;
     let y = player.pos.y as usize / block_size;
     
@@ -55,133 +39,64 @@ pub fn check_victory(player: &Player, maze: &Maze, block_size: usize) -> bool {
     maze[y][x] == 'g'
 }
 
-pub fn process_events(player: &mut Player, rl: &RaylibHandle, maze: &Maze, block_size: usize, audio_manager: &mut AudioManager) {
-    const MOVE_SPEED: f32 = 5.0;
-    const GAMEPAD_MOVE_SPEED: f32 = 8.0; 
-    const ROTATION_SPEED: f32 = PI / 50.0;
-    const MOUSE_SENSITIVITY: f32 = 0.01;
-    const GAMEPAD_SENSITIVITY: f32 = 0.08; 
-    const GAMEPAD_DEADZONE: f32 = 0.1;
-    
-    //Verificar si hay un gamepad conectado
-    let gamepad_available = rl.is_gamepad_available(0);
-    
-    //Variable para detectar si hubo movimiento en este frame
-    let mut movement_occurred = false;
-    
-    //Rotación con mouse (solo horizontal)
-    let mouse_delta = rl.get_mouse_delta();
-    player.a += mouse_delta.x * MOUSE_SENSITIVITY; 
-    
-    //Rotación con teclado
-    if rl.is_key_down(KeyboardKey::KEY_LEFT) {
-        player.a -= ROTATION_SPEED;  //Girar a la izquierda
-    }
-    if rl.is_key_down(KeyboardKey::KEY_RIGHT) {
-        player.a += ROTATION_SPEED;  //Girar a la derecha
-    }
-    
-    //Rotación con gamepad (stick derecho)
-    if gamepad_available {
-        let right_stick_x = rl.get_gamepad_axis_movement(0, GamepadAxis::GAMEPAD_AXIS_RIGHT_X);
-        if right_stick_x.abs() > GAMEPAD_DEADZONE {
-            player.a += right_stick_x * GAMEPAD_SENSITIVITY;
-        }
-    }
-    
-    //Movimiento hacia adelante
-    if rl.is_key_down(KeyboardKey::KEY_UP) || rl.is_key_down(KeyboardKey::KEY_W) {
-        let new_pos = Vector2::new(
-            player.pos.x + MOVE_SPEED * player.a.cos(),
-            player.pos.y + MOVE_SPEED * player.a.sin(),
-        );
-        
-        if is_valid_position(new_pos, maze, block_size) {
-            player.pos = new_pos;
-            movement_occurred = true;
-        }
-    }
-    
-    //Movimiento hacia atrás
-    if rl.is_key_down(KeyboardKey::KEY_DOWN) || rl.is_key_down(KeyboardKey::KEY_S) {
-        let new_pos = Vector2::new(
-            player.pos.x - MOVE_SPEED * player.a.cos(),
-            player.pos.y - MOVE_SPEED * player.a.sin(),
-        );
-        
-        if is_valid_position(new_pos, maze, block_size) {
-            player.pos = new_pos;
-            movement_occurred = true;
-        }
-    }
-    
-    //Movimiento con gamepad (stick izquierdo)
-    if gamepad_available {
-        let left_stick_y = rl.get_gamepad_axis_movement(0, GamepadAxis::GAMEPAD_AXIS_LEFT_Y);
-        if left_stick_y.abs() > GAMEPAD_DEADZONE {
-            let new_pos = Vector2::new(
-                player.pos.x + GAMEPAD_MOVE_SPEED * (-left_stick_y) * player.a.cos(),
-                player.pos.y + GAMEPAD_MOVE_SPEED * (-left_stick_y) * player.a.sin(),
-            );
-            
-            if is_valid_position(new_pos, maze, block_size) {
-                player.pos = new_pos;
-                movement_occurred = true;
-            }
-        }
-    }
-    
-    //Movimiento lateral
-    if rl.is_key_down(KeyboardKey::KEY_A) {
-        let new_pos = Vector2::new(
-            player.pos.x + MOVE_SPEED * (player.a - PI/2.0).cos(),  //Movimiento a la izquierda
-            player.pos.y + MOVE_SPEED * (player.a - PI/2.0).sin(),
-        );
-        
-        if is_valid_position(new_pos, maze, block_size) {
-            player.pos = new_pos;
-            movement_occurred = true;
-        }
-    }
-    
-    if rl.is_key_down(KeyboardKey::KEY_D) {
-        let new_pos = Vector2::new(
-            player.pos.x + MOVE_SPEED * (player.a + PI/2.0).cos(),  //Movimiento a la derecha
-            player.pos.y + MOVE_SPEED * (player.a + PI/2.0).sin(),
-        );
-        
-        if is_valid_position(new_pos, maze, block_size) {
-            player.pos = new_pos;
-            movement_occurred = true;
-        }
+//Velocidades en unidades del mundo por segundo (a 15 FPS equivalen a las de antes)
+const MOVE_SPEED: f32 = 75.0;
+const ROTATION_SPEED: f32 = PI * 0.3; //Radianes por segundo
+//Radio de colisión del jugador; evita pegar la cámara a las paredes
+pub const PLAYER_RADIUS: f32 = 20.0;
+
+//Intención de movimiento leída en un frame; se aplica en cada paso fijo de simulación
+//y es lo que se guarda en las repeticiones
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    pub forward: f32, //Unidades por segundo (negativo hacia atrás)
+    pub strafe: f32,  //Unidades por segundo (positivo a la derecha)
+    pub turn: f32,    //Radianes por segundo
+    pub look: f32,    //Radianes a girar de una vez en este paso (mouse)
+    pub interact: bool, //Usar la puerta o el interruptor de enfrente en este paso
+}
+
+//Traduce las acciones (ya asignadas a teclado, mouse o gamepad) a movimiento
+pub fn read_player_input(input: &InputState) -> PlayerInput {
+    PlayerInput {
+        forward: input.axis(Action::MoveBackward, Action::MoveForward) * MOVE_SPEED,
+        strafe: input.axis(Action::StrafeLeft, Action::StrafeRight) * MOVE_SPEED,
+        turn: input.axis(Action::TurnLeft, Action::TurnRight) * ROTATION_SPEED,
+        look: input.look(),
+        interact: input.is_pressed(Action::Use),
     }
-    
-    //Movimiento lateral con gamepad (stick izquierdo horizontal)
-    if gamepad_available {
-        let left_stick_x = rl.get_gamepad_axis_movement(0, GamepadAxis::GAMEPAD_AXIS_LEFT_X);
-        if left_stick_x.abs() > GAMEPAD_DEADZONE {
-            let angle = if left_stick_x < 0.0 {
-                player.a - PI/2.0  //Movimiento a la izquierda 
-            } else {
-                player.a + PI/2.0  //Movimiento a la derecha
-            };
-            
-            let new_pos = Vector2::new(
-                player.pos.x + GAMEPAD_MOVE_SPEED * left_stick_x.abs() * angle.cos(),
-                player.pos.y + GAMEPAD_MOVE_SPEED * left_stick_x.abs() * angle.sin(),
-            );
-            
-            if is_valid_position(new_pos, maze, block_size) {
-                player.pos = new_pos;
-                movement_occurred = true;
-            }
-        }
+}
+
+//Avanza la simulación del jugador dt segundos; devuelve si hubo movimiento
+pub fn update_player(player: &mut Player, input: &PlayerInput, dt: f32, maze: &Maze, doors: &Doors, block_size: usize) -> bool {
+    player.a += input.look + input.turn * dt;
+    
+    //Desplazamiento combinado hacia adelante y lateral
+    let forward = input.forward * dt;
+    let strafe = input.strafe * dt;
+    let delta = Vector2::new(
+        forward * player.a.cos() + strafe * (player.a + PI/2.0).cos(),
+        forward * player.a.sin() + strafe * (player.a + PI/2.0).sin(),
+    );
+    if delta.x == 0.0 && delta.y == 0.0 {
+        return false;
     }
     
-    //Manejar audio de movimiento
-    if movement_occurred {
-        audio_manager.play_running_sound();
-    } else {
-        audio_manager.stop_running_sound();
+    let new_pos = move_circle(maze, doors, player.pos, delta, PLAYER_RADIUS, block_size);
+    let movement_occurred = new_pos != player.pos;
+    player.pos = new_pos;
+    movement_occurred
+}
+
+//Estado intermedio entre dos pasos de simulación para dibujar sin tirones
+pub fn interpolate_player(previous: &Player, current: &Player, alpha: f32) -> Player {
+    Player {
+        pos: Vector2::new(
+            previous.pos.x + (current.pos.x - previous.pos.x) * alpha,
+            previous.pos.y + (current.pos.y - previous.pos.y) * alpha,
+        ),
+        a: previous.a + (current.a - previous.a) * alpha,
+        fov: current.fov,
+        inventory: current.inventory,
     }
-}
\ No newline at end of file
+}
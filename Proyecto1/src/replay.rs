@@ -0,0 +1,359 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::player::PlayerInput;
+
+pub const REPLAY_DIR: &str = "replays";
+pub const LAST_REPLAY: &str = "replays/last.replay";
+
+//Lo que pasa en un paso de la simulación: el input ya resuelto o un reinicio del nivel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayEvent {
+    Tick(PlayerInput),
+    Reset, //Se reinicia el nivel antes del siguiente paso
+}
+
+//Grabación de una partida: con el mismo nivel, la misma semilla y los mismos pasos
+//la simulación se repite exactamente
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub level: String, //Clave del nivel (ver Difficulty::record_key)
+    pub seed: u64,     //Semilla de fastrand al empezar la partida
+    pub time: Option<f32>, //Tiempo final si la partida llegó a la meta
+    events: Vec<ReplayEvent>,
+}
+
+//Semilla nueva para cada partida
+pub fn new_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+impl Replay {
+    pub fn new(level: &str, seed: u64) -> Self {
+        Replay {
+            level: level.to_string(),
+            seed,
+            time: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn push_tick(&mut self, input: PlayerInput) {
+        self.events.push(ReplayEvent::Tick(input));
+    }
+
+    pub fn push_reset(&mut self) {
+        self.events.push(ReplayEvent::Reset);
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.events.iter().filter(|e| matches!(e, ReplayEvent::Tick(_))).count()
+    }
+
+    //Formato de texto compacto; los pasos iguales seguidos se guardan una sola vez:
+    //  #replay 2
+    //  level = easy
+    //  seed = 1234
+    //  time = 42.3
+    //  [ticks]
+    //  120 75 0 0 0 0    (repeticiones, avance, lateral, giro, mouse, usar)
+    //  reset
+    //La versión 1 no tenía la columna "usar"; se sigue pudiendo leer
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = format!("#replay 2\nlevel = {}\nseed = {}\n", self.level, self.seed);
+        if let Some(time) = self.time {
+            contents.push_str(&format!("time = {}\n", time));
+        }
+        contents.push_str("[ticks]\n");
+
+        let mut iter = self.events.iter().peekable();
+        while let Some(event) = iter.next() {
+            match event {
+                ReplayEvent::Reset => contents.push_str("reset\n"),
+                ReplayEvent::Tick(input) => {
+                    let mut count = 1;
+                    while iter.peek() == Some(&&ReplayEvent::Tick(*input)) {
+                        iter.next();
+                        count += 1;
+                    }
+                    //Display de f32 es el texto más corto que vuelve al mismo valor exacto
+                    contents.push_str(&format!(
+                        "{} {} {} {} {} {}\n",
+                        count, input.forward, input.strafe, input.turn, input.look, input.interact as u8,
+                    ));
+                },
+            }
+        }
+
+        fs::write(path, contents)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut replay = Replay::new("", 0);
+        let mut in_ticks = false;
+        let mut has_level = false;
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: String| format!("{}:{}: {}", path, line_number + 1, message);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if !in_ticks {
+                if line == "[ticks]" {
+                    in_ticks = true;
+                    continue;
+                }
+                let (key, value) = line.split_once('=').ok_or_else(|| error("expected 'key = value'".to_string()))?;
+                let value = value.trim();
+                match key.trim() {
+                    "level" => {
+                        replay.level = value.to_string();
+                        has_level = true;
+                    },
+                    "seed" => replay.seed = value.parse().map_err(|_| error(format!("invalid seed '{}'", value)))?,
+                    "time" => replay.time = Some(value.parse().map_err(|_| error(format!("invalid time '{}'", value)))?),
+                    other => return Err(error(format!("unknown key '{}'", other))),
+                }
+            } else if line == "reset" {
+                replay.push_reset();
+            } else {
+                let numbers: Vec<&str> = line.split_whitespace().collect();
+                if numbers.len() != 5 && numbers.len() != 6 {
+                    return Err(error(format!("expected 5 or 6 values per tick line, found {}", numbers.len())));
+                }
+                let count: usize = numbers[0].parse().map_err(|_| error(format!("invalid count '{}'", numbers[0])))?;
+                let value = |i: usize| numbers[i].parse::<f32>().map_err(|_| error(format!("invalid number '{}'", numbers[i])));
+                let input = PlayerInput {
+                    forward: value(1)?,
+                    strafe: value(2)?,
+                    turn: value(3)?,
+                    look: value(4)?,
+                    interact: match numbers.get(5) {
+                        None | Some(&"0") => false,
+                        Some(&"1") => true,
+                        Some(other) => return Err(error(format!("invalid use flag '{}'", other))),
+                    },
+                };
+                for _ in 0..count {
+                    replay.push_tick(input);
+                }
+            }
+        }
+
+        if !has_level {
+            return Err(format!("{}: missing 'level'", path));
+        }
+        Ok(replay)
+    }
+}
+
+//Reproduce los eventos de una grabación en orden
+#[derive(Debug, Clone)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    position: usize,
+    ticks_played: usize,
+    total_ticks: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let total_ticks = replay.tick_count();
+        ReplayPlayer { replay, position: 0, ticks_played: 0, total_ticks }
+    }
+
+    pub fn next_event(&mut self) -> Option<ReplayEvent> {
+        let event = self.replay.events.get(self.position).copied();
+        self.position += 1;
+        if let Some(ReplayEvent::Tick(_)) = event {
+            self.ticks_played += 1;
+        }
+        event
+    }
+
+    pub fn ticks_played(&self) -> usize {
+        self.ticks_played
+    }
+
+    pub fn total_ticks(&self) -> usize {
+        self.total_ticks
+    }
+
+    pub fn rewind(&mut self) {
+        self.position = 0;
+        self.ticks_played = 0;
+    }
+}
+
+//Guarda la última partida jugada para poder verla desde el menú
+pub fn save_last_run(replay: &Replay) {
+    if let Err(e) = replay.save(LAST_REPLAY) {
+        println!("No se pudo guardar {}: {}", LAST_REPLAY, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raylib::prelude::*;
+    use std::f32::consts::PI;
+    use crate::framebuffer::Framebuffer;
+    use crate::level::{Level, load_level};
+    use crate::items::Inventory;
+    use crate::player::Player;
+    use crate::sprites::SpriteManager;
+    use crate::stats::RunStats;
+    use crate::{reset_level, simulate_step};
+
+    const BLOCK_SIZE: usize = 100;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("{}-{}.replay", name, std::process::id())).to_string_lossy().into_owned()
+    }
+
+    fn input(forward: f32, strafe: f32, turn: f32, look: f32, interact: bool) -> PlayerInput {
+        PlayerInput { forward, strafe, turn, look, interact }
+    }
+
+    #[test]
+    fn save_and_load_keep_events() {
+        let mut replay = Replay::new("random-42", 1234);
+        replay.time = Some(12.345);
+        //Pasos repetidos (se guardan en una sola línea), valores sin representación corta y reinicios
+        for _ in 0..3 {
+            replay.push_tick(input(150.0, 0.0, 0.0, 0.0, false));
+        }
+        replay.push_reset();
+        replay.push_tick(input(-0.1, 1e-7, -2.5, 0.0123, true));
+        replay.push_tick(input(150.0, 0.0, 0.0, 0.0, false));
+        replay.push_reset();
+        replay.push_reset();
+
+        let path = temp_path("save_and_load");
+        replay.save(&path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(contents.contains("\n3 150 0 0 0 0\nreset\n"));
+        assert_eq!(loaded.level, replay.level);
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.time, replay.time);
+        assert_eq!(loaded.events, replay.events);
+        assert_eq!(loaded.tick_count(), 5);
+    }
+
+    #[test]
+    fn load_reads_version_1() {
+        //Las grabaciones anteriores a las puertas no tienen la columna de Use
+        let path = temp_path("version_1");
+        fs::write(&path, "#replay 1\nlevel = easy\nseed = 7\n[ticks]\n2 150 0 3 0\nreset\n1 0 -75 0 0.5\n").unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let forward = ReplayEvent::Tick(input(150.0, 0.0, 3.0, 0.0, false));
+        let strafe = ReplayEvent::Tick(input(0.0, -75.0, 0.0, 0.5, false));
+        assert_eq!(loaded.level, "easy");
+        assert_eq!(loaded.seed, 7);
+        assert_eq!(loaded.time, None);
+        assert_eq!(loaded.events, vec![forward, forward, ReplayEvent::Reset, strafe]);
+    }
+
+    #[test]
+    fn load_rejects_wrong_column_count() {
+        let path = temp_path("bad_columns");
+        fs::write(&path, "#replay 2\nlevel = easy\n[ticks]\n1 150 0\n").unwrap();
+        let error = Replay::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert!(error.ends_with(":4: expected 5 or 6 values per tick line, found 3"), "{}", error);
+    }
+
+    //Lo que tiene que coincidir entre la partida y su repetición
+    struct RunResult {
+        run: RunStats,
+        pos: Vector2,
+        a: f32,
+    }
+
+    //Nivel recién cargado con la semilla de la partida, igual que start_run
+    fn start(seed: u64) -> (Level, Player, SpriteManager, Framebuffer) {
+        let mut level = load_level("maze_easy.txt").unwrap();
+        let mut player = Player { pos: Vector2::zero(), a: PI / 3.0, fov: PI / 3.0, inventory: Inventory::default() };
+        let mut sprite_manager = SpriteManager::new();
+        let mut framebuffer = Framebuffer::new(8, 8);
+        fastrand::seed(seed);
+        reset_level(&mut level, &mut player, &mut sprite_manager, &mut framebuffer, BLOCK_SIZE);
+        (level, player, sprite_manager, framebuffer)
+    }
+
+    //Juega una partida con input variado y la graba como lo hace el juego
+    fn record(seed: u64) -> (Replay, RunResult) {
+        let (mut level, mut player, mut sprite_manager, mut framebuffer) = start(seed);
+        let mut replay = Replay::new("easy", seed);
+        let mut run = RunStats::default();
+
+        for step in 0..600 {
+            if step == 400 {
+                run.resets += 1;
+                replay.push_reset();
+                reset_level(&mut level, &mut player, &mut sprite_manager, &mut framebuffer, BLOCK_SIZE);
+            }
+            let tick = input(
+                if step % 90 < 60 { 150.0 } else { -40.0 },
+                if step % 50 < 10 { 75.0 } else { 0.0 },
+                if step % 120 < 30 { 2.0 } else { -0.3 },
+                if step % 7 == 0 { 0.013 } else { 0.0 },
+                step % 45 == 0,
+            );
+            replay.push_tick(tick);
+            simulate_step(&mut run, &mut level, &mut player, &mut sprite_manager, &tick, BLOCK_SIZE);
+        }
+
+        (replay, RunResult { run, pos: player.pos, a: player.a })
+    }
+
+    //Reproduce los eventos igual que run_headless
+    fn play(replay: &Replay) -> RunResult {
+        let (mut level, mut player, mut sprite_manager, mut framebuffer) = start(replay.seed);
+        let mut playback = ReplayPlayer::new(replay.clone());
+        let mut run = RunStats::default();
+
+        while let Some(event) = playback.next_event() {
+            match event {
+                ReplayEvent::Tick(input) => {
+                    simulate_step(&mut run, &mut level, &mut player, &mut sprite_manager, &input, BLOCK_SIZE);
+                },
+                ReplayEvent::Reset => {
+                    run.resets += 1;
+                    reset_level(&mut level, &mut player, &mut sprite_manager, &mut framebuffer, BLOCK_SIZE);
+                },
+            }
+        }
+
+        RunResult { run, pos: player.pos, a: player.a }
+    }
+
+    #[test]
+    fn replay_reproduces_the_run() {
+        let (replay, recorded) = record(99);
+        assert!(recorded.run.distance > 1.0, "la partida de prueba tiene que moverse");
+
+        let path = temp_path("deterministic");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let replayed = play(&loaded);
+        assert_eq!(replayed.run, recorded.run);
+        assert_eq!(replayed.pos, recorded.pos);
+        assert_eq!(replayed.a, recorded.a);
+    }
+}
@@ -1,9 +1,12 @@
 use raylib::prelude::*;
 use crate::framebuffer::Framebuffer;
 use crate::game_state::{GameManager, Difficulty, GameState};
-use crate::maze::load_maze;
 use crate::player::Player;
 use crate::audio::AudioManager;
+use crate::input::{Action, InputMap, InputState, INPUT_CONFIG, capture_binding};
+use crate::settings::{Settings, SettingsRow, SETTINGS_FILE};
+use crate::replay::{Replay, LAST_REPLAY, REPLAY_DIR, save_last_run};
+use crate::stats::{Leaderboard, Record, format_time, LEADERBOARD_FILE, LEADERBOARD_SIZE, INITIALS_LENGTH};
 use std::f32::consts::PI;
 
 pub fn draw_welcome_screen(framebuffer: &mut Framebuffer, game_manager: &GameManager) {
@@ -17,7 +20,7 @@ pub fn draw_welcome_screen(framebuffer: &mut Framebuffer, game_manager: &GameMan
         for y in 0..framebuffer.height {
             for x in 0..framebuffer.width {
                 let color = img.get_color(x as i32, y as i32);
-                framebuffer.color_buffer.draw_pixel(x as i32, y as i32, color);
+                framebuffer.set_pixel_color(x, y, color);
             }
         }
     }
@@ -71,19 +74,24 @@ pub fn draw_welcome_screen(framebuffer: &mut Framebuffer, game_manager: &GameMan
     //Subtítulo en gris claro
     framebuffer.draw_text(subtitle_text, subtitle_x, center_y - 79, subtitle_char_width, Color::LIGHTGRAY);
     
-    //Opciones de dificultad
+    //Opciones de dificultad y las pantallas de controles y ajustes al final
+    let random = Difficulty::Random(game_manager.random_options.clone());
     let options = [
-        Difficulty::Easy,
-        Difficulty::Medium,
-        Difficulty::Difficult,
+        Difficulty::Easy.get_name(),
+        Difficulty::Medium.get_name(),
+        Difficulty::Difficult.get_name(),
+        random.get_name(),
+        "REPLAY",
+        "CONTROLS",
+        "SETTINGS",
     ];
     
-    for (i, difficulty) in options.iter().enumerate() {
-        let option_text = difficulty.get_name();
+    for (i, option_text) in options.iter().enumerate() {
+        let option_text = *option_text;
         let option_char_width = 24;
         let option_width = option_text.len() as u32 * option_char_width;
         let option_x = menu_x + (menu_width.saturating_sub(option_width)) / 2;
-        let option_y = center_y - 30 + (i as u32 * 40);
+        let option_y = center_y - 55 + (i as u32 * 30);
         
         //Color según si está seleccionado
         let (text_color, border_color) = if i == game_manager.selected_option {
@@ -143,21 +151,65 @@ pub fn draw_welcome_screen(framebuffer: &mut Framebuffer, game_manager: &GameMan
     framebuffer.draw_text(instructions1, instr1_x, center_y + 150, instr_char_width, Color::SKYBLUE);
     framebuffer.draw_text(instructions2, instr2_x, center_y + 170, instr_char_width, Color::SKYBLUE);
     framebuffer.draw_text(instructions3, instr3_x, center_y + 190, instr_char_width, Color::SKYBLUE);
+    
+    //Error del último nivel que no se pudo cargar
+    if let Some(ref error) = game_manager.load_error {
+        draw_error_message(framebuffer, error);
+    }
 }
 
-pub fn draw_victory_screen(framebuffer: &mut Framebuffer) {
+fn draw_error_message(framebuffer: &mut Framebuffer, message: &str) {
+    let char_width = 14;
+    let line_height = 20;
+    let margin = 20;
+    let chars_per_line = ((framebuffer.width - 2 * margin) / char_width).max(1) as usize;
+    
+    //Partir el mensaje en líneas que quepan en la pantalla
+    let chars: Vec<char> = message.chars().collect();
+    let lines: Vec<String> = chars.chunks(chars_per_line).map(|chunk| chunk.iter().collect()).collect();
+    
+    let box_height = lines.len() as u32 * line_height + margin;
+    let box_y = framebuffer.height.saturating_sub(box_height + margin);
+    
+    //Fondo rojo oscuro semitransparente
+    framebuffer.set_current_color(Color::new(80, 0, 0, 220));
+    for x in margin / 2..framebuffer.width - margin / 2 {
+        for y in box_y..box_y + box_height {
+            framebuffer.set_pixel(x, y);
+        }
+    }
+    
+    for (i, line) in lines.iter().enumerate() {
+        let y = box_y + margin / 2 + i as u32 * line_height;
+        framebuffer.draw_text(line, margin, y, char_width, Color::WHITE);
+    }
+}
+
+//Texto centrado con borde negro para que se lea sobre la imagen de fondo
+fn draw_centered_text(framebuffer: &mut Framebuffer, text: &str, center_x: u32, y: u32, char_width: u32, color: Color) {
+    let x = center_x.saturating_sub(text.chars().count() as u32 * char_width / 2);
+    framebuffer.draw_text(text, x + 1, y + 1, char_width, Color::BLACK);
+    framebuffer.draw_text(text, x.saturating_sub(1), y.saturating_sub(1), char_width, Color::BLACK);
+    framebuffer.draw_text(text, x, y, char_width, color);
+}
+
+pub fn draw_victory_screen(
+    framebuffer: &mut Framebuffer,
+    game_manager: &GameManager,
+    leaderboard: &Leaderboard,
+    input_map: &InputMap,
+) {
     //Usar la imagen de fondo en lugar de color verde
     //(La imagen ya estará cargada en el framebuffer desde el main)
     
-    //Dibujar mensaje de éxito centrado
     let center_x = framebuffer.width / 2;
     let center_y = framebuffer.height / 2;
     
     framebuffer.set_current_color(Color::new(0, 0, 0, 180)); 
     
-    //Dibujar rectángulo de fondo para el texto
-    let rect_width = 600;
-    let rect_height = 200;
+    //Dibujar rectángulo de fondo para resultados y récords
+    let rect_width = 700;
+    let rect_height = 560;
     let rect_x = center_x.saturating_sub(rect_width / 2);
     let rect_y = center_y.saturating_sub(rect_height / 2);
     
@@ -169,63 +221,106 @@ pub fn draw_victory_screen(framebuffer: &mut Framebuffer) {
         }
     }
     
-    //"SUCCESS!" - texto principal con borde para mejor contraste
-    let success_text = "SUCCESS!";
-    let char_width = 32;
-    let text_width = success_text.len() as u32 * char_width;
-    let start_x = center_x.saturating_sub(text_width / 2);
-    
-    //Dibujar borde negro del texto
-    framebuffer.draw_text(success_text, start_x + 2, center_y - 58, char_width, Color::BLACK);
-    framebuffer.draw_text(success_text, start_x - 2, center_y - 58, char_width, Color::BLACK);
-    framebuffer.draw_text(success_text, start_x, center_y - 58, char_width, Color::BLACK);
-    framebuffer.draw_text(success_text, start_x, center_y - 62, char_width, Color::BLACK);
-    
-    //Texto principal en blanco
-    framebuffer.draw_text(success_text, start_x, center_y - 60, char_width, Color::WHITE);
-    
-    //"You reached the goal!" - texto secundario con borde
-    let goal_text = "You reached the goal!";
-    let char_width_small = 20;
-    let text_width_small = goal_text.len() as u32 * char_width_small;
-    let start_x_small = center_x.saturating_sub(text_width_small / 2);
+    //"SUCCESS!" - texto principal
+    draw_centered_text(framebuffer, "SUCCESS!", center_x, rect_y + 20, 32, Color::WHITE);
+    draw_centered_text(framebuffer, "You reached the goal!", center_x, rect_y + 66, 20, Color::WHITE);
     
-    //Borde negro
-    framebuffer.draw_text(goal_text, start_x_small + 1, center_y + 9, char_width_small, Color::BLACK);
-    framebuffer.draw_text(goal_text, start_x_small - 1, center_y + 9, char_width_small, Color::BLACK);
-    framebuffer.draw_text(goal_text, start_x_small, center_y + 9, char_width_small, Color::BLACK);
-    framebuffer.draw_text(goal_text, start_x_small, center_y + 11, char_width_small, Color::BLACK);
-    
-    //Texto en blanco
-    framebuffer.draw_text(goal_text, start_x_small, center_y + 10, char_width_small, Color::WHITE);
-    
-    //"Press M for Menu or R to restart" - instrucciones con borde
-    let restart_text = "Press M for Menu or R to restart";
-    let restart_width = restart_text.len() as u32 * 16;
-    let start_x_restart = center_x.saturating_sub(restart_width / 2);
-    
-    //Borde negro
-    framebuffer.draw_text(restart_text, start_x_restart + 1, center_y + 49, 16, Color::BLACK);
-    framebuffer.draw_text(restart_text, start_x_restart - 1, center_y + 49, 16, Color::BLACK);
-    framebuffer.draw_text(restart_text, start_x_restart, center_y + 49, 16, Color::BLACK);
-    framebuffer.draw_text(restart_text, start_x_restart, center_y + 51, 16, Color::BLACK);
-    
-    //Texto en amarillo
-    framebuffer.draw_text(restart_text, start_x_restart, center_y + 50, 16, Color::YELLOW);
+    //Resultados de la partida
+    let run = &game_manager.run;
+    let mut run_text = format!("Time {}  Distance {:.1}  Resets {}", format_time(run.elapsed), run.distance, run.resets);
+    if run.catches > 0 {
+        run_text.push_str(&format!("  Caught {}", run.catches));
+    }
+    run_text.push_str(&format!("  Explored {:.0}%", run.explored));
+    draw_centered_text(framebuffer, &run_text, center_x, rect_y + 104, 16, Color::SKYBLUE);
+    
+    //Mejor tiempo personal: mientras se escriben las iniciales se consulta con las actuales
+    let level_key = game_manager.current_difficulty.record_key();
+    let initials: String = game_manager.initials.iter().collect();
+    let (best, new_best) = match game_manager.last_record {
+        Some(ref record) => (game_manager.previous_best, game_manager.previous_best.is_none_or(|best| record.time < best)),
+        None => {
+            let best = leaderboard.personal_best(&level_key, &initials);
+            (best, best.is_none_or(|best| run.elapsed < best))
+        },
+    };
+    let (best_text, best_color) = match (&game_manager.playback, best) {
+        //Una repetición compara su tiempo con el grabado para verificar el récord
+        (Some(playback), _) => match playback.replay.time {
+            Some(time) if time == run.elapsed => (format!("Replay verified: {}", format_time(time)), Color::GREEN),
+            Some(time) => (format!("Replay MISMATCH: recorded {}", format_time(time)), Color::RED),
+            None => ("Replay of an unfinished run".to_string(), Color::LIGHTGRAY),
+        },
+        (None, Some(best)) if new_best => (format!("NEW PERSONAL BEST! (previous {})", format_time(best)), Color::new(255, 215, 0, 255)),
+        (None, Some(best)) => (format!("Personal best: {}", format_time(best)), Color::LIGHTGRAY),
+        (None, None) => ("First finish on this level!".to_string(), Color::new(255, 215, 0, 255)),
+    };
+    draw_centered_text(framebuffer, &best_text, center_x, rect_y + 132, 14, best_color);
+    
+    //Iniciales: editables hasta confirmar, luego solo se muestran
+    if game_manager.entering_initials {
+        let label = "Initials: ";
+        let char_width = 22;
+        let total = (label.len() + 2 * INITIALS_LENGTH) as u32 * char_width;
+        let x = center_x.saturating_sub(total / 2);
+        let y = rect_y + 164;
+        framebuffer.draw_text(label, x, y, char_width, Color::WHITE);
+        for (i, ch) in game_manager.initials.iter().enumerate() {
+            let ch_x = x + (label.len() + 2 * i) as u32 * char_width;
+            let color = if i == game_manager.initials_cursor { Color::YELLOW } else { Color::WHITE };
+            framebuffer.draw_text(&ch.to_string(), ch_x, y, char_width, color);
+            if i == game_manager.initials_cursor {
+                framebuffer.draw_text("_", ch_x, y + 6, char_width, Color::YELLOW);
+            }
+        }
+    } else if game_manager.playback.is_some() {
+        draw_centered_text(framebuffer, "REPLAY", center_x, rect_y + 168, 16, Color::WHITE);
+    } else {
+        draw_centered_text(framebuffer, &format!("Saved as {}", initials), center_x, rect_y + 168, 16, Color::WHITE);
+    }
     
-    //"Start/Select for Menu/Restart" - instrucciones de gamepad
-    let gamepad_text = "Start/Select for Menu/Restart";
-    let gamepad_width = gamepad_text.len() as u32 * 14;
-    let start_x_gamepad = center_x.saturating_sub(gamepad_width / 2);
+    //Tabla de los mejores tiempos del nivel
+    let level_name = match game_manager.current_difficulty {
+        Difficulty::Random(ref options) => format!("RANDOM (seed {})", options.seed),
+        ref difficulty => difficulty.get_name().to_string(),
+    };
+    draw_centered_text(framebuffer, &format!("TOP {} - {}", LEADERBOARD_SIZE, level_name), center_x, rect_y + 208, 18, Color::new(255, 215, 0, 255));
+    
+    let table_x = center_x.saturating_sub(230);
+    let columns = [0, 60, 150, 320, 470];
+    let header = ["#", "NAME", "TIME", "DISTANCE", "RESETS"];
+    for (column, text) in columns.iter().zip(header) {
+        framebuffer.draw_text(text, table_x + column, rect_y + 240, 14, Color::LIGHTGRAY);
+    }
     
-    //Borde negro
-    framebuffer.draw_text(gamepad_text, start_x_gamepad + 1, center_y + 69, 14, Color::BLACK);
-    framebuffer.draw_text(gamepad_text, start_x_gamepad - 1, center_y + 69, 14, Color::BLACK);
-    framebuffer.draw_text(gamepad_text, start_x_gamepad, center_y + 69, 14, Color::BLACK);
-    framebuffer.draw_text(gamepad_text, start_x_gamepad, center_y + 71, 14, Color::BLACK);
+    let records = leaderboard.top(&level_key);
+    if records.is_empty() {
+        draw_centered_text(framebuffer, "No records yet", center_x, rect_y + 266, 14, Color::WHITE);
+    }
+    for (i, record) in records.iter().enumerate() {
+        let y = rect_y + 264 + i as u32 * 22;
+        let color = if game_manager.last_record.as_ref() == Some(*record) { Color::YELLOW } else { Color::WHITE };
+        let cells = [
+            format!("{}", i + 1),
+            record.initials.clone(),
+            format_time(record.time),
+            format!("{:.1}", record.distance),
+            format!("{}", record.resets),
+        ];
+        for (column, text) in columns.iter().zip(cells) {
+            framebuffer.draw_text(&text, table_x + column, y, 14, color);
+        }
+    }
     
-    //Texto en cian
-    framebuffer.draw_text(gamepad_text, start_x_gamepad, center_y + 70, 14, Color::SKYBLUE);
+    //Instrucciones con las teclas asignadas
+    if game_manager.entering_initials {
+        draw_centered_text(framebuffer, "Type your initials or use the arrows/stick", center_x, rect_y + 500, 14, Color::YELLOW);
+        draw_centered_text(framebuffer, &format!("Press {} to save", input_map.key_name(Action::Confirm)), center_x, rect_y + 522, 14, Color::SKYBLUE);
+    } else {
+        let restart_text = format!("Press {} for Menu or {} to restart", input_map.key_name(Action::Menu), input_map.key_name(Action::Reset));
+        draw_centered_text(framebuffer, &restart_text, center_x, rect_y + 500, 16, Color::YELLOW);
+        draw_centered_text(framebuffer, "Start/Select for Menu/Restart", center_x, rect_y + 522, 14, Color::SKYBLUE);
+    }
 }
 
 #[derive(PartialEq)]
@@ -237,27 +332,104 @@ pub enum VictoryAction {
 
 pub fn handle_victory_input(
     game_manager: &mut GameManager,
-    window: &RaylibHandle,
+    input: &InputState,
+    window: &mut RaylibHandle,
+    leaderboard: &mut Leaderboard,
     audio_manager: &AudioManager,
 ) -> VictoryAction {
+    if game_manager.entering_initials {
+        handle_initials_input(game_manager, input, window, leaderboard, audio_manager);
+        return VictoryAction::None;
+    }
+    
     //Manejar input de victoria
-    if window.is_key_pressed(KeyboardKey::KEY_M) || 
-       (window.is_gamepad_available(0) && window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT)) {
+    if input.is_pressed(Action::Menu) {
         audio_manager.play_menu_sound();
         game_manager.reset_to_welcome();
         VictoryAction::BackToMenu
-    } else if window.is_key_pressed(KeyboardKey::KEY_R) ||
-             (window.is_gamepad_available(0) && window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT)) {
+    } else if input.is_pressed(Action::Reset) {
         audio_manager.play_start_sound();
         //Señalar que se debe reiniciar el nivel
-        game_manager.state = GameState::Playing;
+        game_manager.restart_run();
         VictoryAction::RestartLevel
     } else {
         VictoryAction::None
     }
 }
 
-pub fn render_victory_screen(framebuffer: &mut Framebuffer) {
+//Letras escritas con el teclado o elegidas con arriba/abajo; al confirmar se guarda el récord
+fn handle_initials_input(
+    game_manager: &mut GameManager,
+    input: &InputState,
+    window: &mut RaylibHandle,
+    leaderboard: &mut Leaderboard,
+    audio_manager: &AudioManager,
+) {
+    let cursor = game_manager.initials_cursor;
+    
+    //Solo pulsaciones nuevas: si se llegó a la meta con W apretada la repetición no escribe letras
+    while let Some(key) = window.get_key_pressed() {
+        let code = key as u32;
+        if (KeyboardKey::KEY_A as u32..=KeyboardKey::KEY_Z as u32).contains(&code) ||
+            (KeyboardKey::KEY_ZERO as u32..=KeyboardKey::KEY_NINE as u32).contains(&code) {
+            game_manager.initials[game_manager.initials_cursor] = char::from_u32(code).unwrap_or('A');
+            game_manager.initials_cursor = (game_manager.initials_cursor + 1).min(INITIALS_LENGTH - 1);
+        }
+    }
+    
+    if window.is_key_pressed(KeyboardKey::KEY_BACKSPACE) || input.is_pressed(Action::MenuLeft) {
+        game_manager.initials_cursor = cursor.saturating_sub(1);
+    }
+    if input.is_pressed(Action::MenuRight) {
+        game_manager.initials_cursor = (cursor + 1).min(INITIALS_LENGTH - 1);
+    }
+    
+    //Recorrer el alfabeto en la letra actual (útil con gamepad)
+    let letter = &mut game_manager.initials[game_manager.initials_cursor];
+    let index = if letter.is_ascii_uppercase() { *letter as u8 - b'A' } else { 0 };
+    if input.is_pressed(Action::MenuUp) {
+        *letter = (b'A' + (index + 25) % 26) as char;
+        audio_manager.play_up_down_sound();
+    }
+    if input.is_pressed(Action::MenuDown) {
+        *letter = (b'A' + (index + 1) % 26) as char;
+        audio_manager.play_up_down_sound();
+    }
+    
+    if input.is_pressed(Action::Confirm) {
+        let record = Record {
+            level: game_manager.current_difficulty.record_key(),
+            initials: game_manager.initials.iter().collect(),
+            time: game_manager.run.elapsed,
+            distance: game_manager.run.distance,
+            resets: game_manager.run.resets,
+        };
+        game_manager.previous_best = leaderboard.personal_best(&record.level, &record.initials);
+        leaderboard.add(record.clone());
+        if let Err(e) = leaderboard.save(LEADERBOARD_FILE) {
+            println!("No se pudo guardar {}: {}", LEADERBOARD_FILE, e);
+        }
+        
+        //Cada récord queda con su repetición para poder verificar el tiempo
+        let replay_path = format!(
+            "{}/{}_{}_{}.replay",
+            REPLAY_DIR, record.level, record.initials, (record.time * 100.0).round() as u32,
+        );
+        if let Err(e) = game_manager.recording.save(&replay_path) {
+            println!("No se pudo guardar {}: {}", replay_path, e);
+        }
+        game_manager.last_record = Some(record);
+        game_manager.entering_initials = false;
+        audio_manager.play_menu_sound();
+    }
+}
+
+pub fn render_victory_screen(
+    framebuffer: &mut Framebuffer,
+    game_manager: &GameManager,
+    leaderboard: &Leaderboard,
+    input_map: &InputMap,
+) {
     //Cargar imagen de fondo de victoria
     let success_image = Image::load_image("assets/img/success_screen.jpg");
     if let Ok(mut img) = success_image {
@@ -265,70 +437,453 @@ pub fn render_victory_screen(framebuffer: &mut Framebuffer) {
         for y in 0..framebuffer.height {
             for x in 0..framebuffer.width {
                 let color = img.get_color(x as i32, y as i32);
-                framebuffer.color_buffer.draw_pixel(x as i32, y as i32, color);
+                framebuffer.set_pixel_color(x, y, color);
             }
         }
     }
     
     //Dibujar pantalla de victoria
-    draw_victory_screen(framebuffer);
+    draw_victory_screen(framebuffer, game_manager, leaderboard, input_map);
+}
+
+//Oscurece el último frame del juego para dibujar un menú encima
+fn darken_scene(framebuffer: &mut Framebuffer) {
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            if let Some(color) = framebuffer.get_pixel(x, y) {
+                framebuffer.set_pixel_color(x, y, Color::new(color.r / 3, color.g / 3, color.b / 3, 255));
+            }
+        }
+    }
+}
+
+//Pantalla de derrota dibujada sobre la escena en la que se perdió
+pub fn draw_defeat_screen(framebuffer: &mut Framebuffer, game_manager: &GameManager, input_map: &InputMap) {
+    darken_scene(framebuffer);
+    
+    let center_x = framebuffer.width / 2;
+    let center_y = framebuffer.height / 2;
+    let panel_width = 560;
+    let panel_height = 240;
+    let panel_x = center_x.saturating_sub(panel_width / 2);
+    let panel_y = center_y.saturating_sub(panel_height / 2);
+    framebuffer.set_current_color(Color::new(0, 0, 0, 220));
+    for x in panel_x..(panel_x + panel_width).min(framebuffer.width) {
+        for y in panel_y..(panel_y + panel_height).min(framebuffer.height) {
+            framebuffer.set_pixel(x, y);
+        }
+    }
+    
+    draw_centered_text(framebuffer, "DEFEAT", center_x, panel_y + 20, 32, Color::RED);
+    let reason = game_manager.defeat.as_ref().map_or("You lost!", |fail| fail.reason());
+    draw_centered_text(framebuffer, reason, center_x, panel_y + 70, 20, Color::WHITE);
+    
+    let run = &game_manager.run;
+    let mut run_text = format!("Time {}  Distance {:.1}  Resets {}", format_time(run.elapsed), run.distance, run.resets);
+    if run.catches > 0 {
+        run_text.push_str(&format!("  Caught {}", run.catches));
+    }
+    draw_centered_text(framebuffer, &run_text, center_x, panel_y + 110, 16, Color::SKYBLUE);
+    if game_manager.playback.is_some() {
+        draw_centered_text(framebuffer, "REPLAY", center_x, panel_y + 140, 16, Color::WHITE);
+    }
+    
+    let restart_text = format!("Press {} for Menu or {} to try again", input_map.key_name(Action::Menu), input_map.key_name(Action::Reset));
+    draw_centered_text(framebuffer, &restart_text, center_x, panel_y + 180, 16, Color::YELLOW);
+    draw_centered_text(framebuffer, "Start/Select for Menu/Restart", center_x, panel_y + 204, 14, Color::SKYBLUE);
+}
+
+//Mismas opciones que al ganar: volver al menú o jugar otra vez
+pub fn handle_defeat_input(game_manager: &mut GameManager, input: &InputState, audio_manager: &AudioManager) -> VictoryAction {
+    if input.is_pressed(Action::Menu) {
+        audio_manager.play_menu_sound();
+        game_manager.reset_to_welcome();
+        VictoryAction::BackToMenu
+    } else if input.is_pressed(Action::Reset) {
+        audio_manager.play_start_sound();
+        game_manager.restart_run();
+        VictoryAction::RestartLevel
+    } else {
+        VictoryAction::None
+    }
 }
 
-pub fn handle_welcome_input(game_manager: &mut GameManager, window: &RaylibHandle, audio_manager: &AudioManager) {
-    //Navegación con teclado
-    if window.is_key_pressed(KeyboardKey::KEY_UP) {
+//Cantidad de opciones del menú principal (dificultades + repetición + controles + ajustes)
+const MENU_OPTIONS: usize = 7;
+const REPLAY_OPTION: usize = 4;
+const CONTROLS_OPTION: usize = 5;
+const SETTINGS_OPTION: usize = 6;
+
+pub fn handle_welcome_input(game_manager: &mut GameManager, input: &InputState, audio_manager: &AudioManager) {
+    //Navegación con las acciones del menú (teclado o gamepad según la configuración)
+    if input.is_pressed(Action::MenuUp) {
         audio_manager.play_up_down_sound();
         game_manager.selected_option = if game_manager.selected_option > 0 {
             game_manager.selected_option - 1
         } else {
-            2 //Volver al final
+            MENU_OPTIONS - 1 //Volver al final
         };
     }
     
-    if window.is_key_pressed(KeyboardKey::KEY_DOWN) {
+    if input.is_pressed(Action::MenuDown) {
         audio_manager.play_up_down_sound();
-        game_manager.selected_option = (game_manager.selected_option + 1) % 3;
+        game_manager.selected_option = (game_manager.selected_option + 1) % MENU_OPTIONS;
     }
     
-    //Navegación con gamepad
-    if window.is_gamepad_available(0) {
-        let left_stick_y = window.get_gamepad_axis_movement(0, GamepadAxis::GAMEPAD_AXIS_LEFT_Y);
-        static mut LAST_STICK_INPUT: f32 = 0.0;
-        
-        unsafe {
-            if left_stick_y < -0.5 && LAST_STICK_INPUT >= -0.5 {
-                audio_manager.play_up_down_sound();
-                game_manager.selected_option = if game_manager.selected_option > 0 {
-                    game_manager.selected_option - 1
-                } else {
-                    2
-                };
-            } else if left_stick_y > 0.5 && LAST_STICK_INPUT <= 0.5 {
-                audio_manager.play_up_down_sound();
-                game_manager.selected_option = (game_manager.selected_option + 1) % 3;
-            }
-            LAST_STICK_INPUT = left_stick_y;
-        }
-    }
-    
-    //Selección con Enter o botón A del gamepad
-    if window.is_key_pressed(KeyboardKey::KEY_ENTER) || 
-       (window.is_gamepad_available(0) && window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN)) {
+    //Selección
+    if input.is_pressed(Action::Confirm) {
         audio_manager.play_start_sound();
         let difficulty = match game_manager.selected_option {
             0 => Difficulty::Easy,
             1 => Difficulty::Medium,
             2 => Difficulty::Difficult,
-            _ => Difficulty::Easy,
+            3 => game_manager.random_difficulty(),
+            REPLAY_OPTION => {
+                //Ver la última partida; el nivel se carga en el main igual que al elegir dificultad
+                let result = Replay::load(LAST_REPLAY).and_then(|replay| game_manager.start_replay(replay));
+                if let Err(e) = result {
+                    game_manager.load_error = Some(e);
+                }
+                return;
+            },
+            CONTROLS_OPTION => {
+                game_manager.open_controls();
+                return;
+            },
+            _ => {
+                game_manager.open_settings();
+                return;
+            },
         };
         game_manager.start_game(difficulty);
     }
     
-    //Controles adicionales para el menú con gamepad
-    if window.is_gamepad_available(0) {
-        //Botón Circle/B para salir del juego
-        if window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT) {
-            std::process::exit(0);
+    //Salir del juego
+    if input.is_pressed(Action::Quit) {
+        std::process::exit(0);
+    }
+}
+
+pub fn draw_controls_screen(framebuffer: &mut Framebuffer, game_manager: &GameManager, input_map: &InputMap) {
+    //Fondo oscuro
+    framebuffer.set_current_color(Color::new(20, 20, 40, 255));
+    for x in 0..framebuffer.width {
+        for y in 0..framebuffer.height {
+            framebuffer.set_pixel(x, y);
+        }
+    }
+    
+    let title_text = "CONTROLS";
+    let title_char_width = 28;
+    let title_x = (framebuffer.width.saturating_sub(title_text.len() as u32 * title_char_width)) / 2;
+    framebuffer.draw_text(title_text, title_x + 2, 42, title_char_width, Color::BLACK);
+    framebuffer.draw_text(title_text, title_x, 40, title_char_width, Color::new(255, 215, 0, 255));
+    
+    //Una fila por acción y al final las opciones de restaurar y volver
+    let first_row = 110;
+    let name_x = 120;
+    let bindings_x = 420;
+    let rows = Action::ALL.len() + 2;
+    //Achicar las filas en resoluciones bajas para que quepan sobre las instrucciones
+    let row_height = (framebuffer.height.saturating_sub(100 + first_row) / rows as u32).min(36);
+    
+    for row in 0..rows {
+        let y = first_row + row as u32 * row_height;
+        let selected = row == game_manager.controls_selected;
+        let color = if selected { Color::YELLOW } else { Color::WHITE };
+        
+        if selected {
+            framebuffer.draw_text(">", name_x - 40, y, 18, Color::YELLOW);
+        }
+        
+        if let Some(action) = Action::ALL.get(row) {
+            framebuffer.draw_text(action.get_name(), name_x, y, 18, color);
+            let bindings = if selected && game_manager.capturing_binding {
+                "Press a key, mouse button or gamepad input...".to_string()
+            } else {
+                input_map.describe(*action)
+            };
+            let bindings_color = if selected && game_manager.capturing_binding { Color::ORANGE } else { Color::LIGHTGRAY };
+            framebuffer.draw_text(&bindings, bindings_x, y + 2, 14, bindings_color);
+        } else if row == Action::ALL.len() {
+            framebuffer.draw_text("RESET DEFAULTS", name_x, y, 18, color);
+        } else {
+            framebuffer.draw_text("SAVE AND BACK", name_x, y, 18, color);
         }
     }
+    
+    //Instrucciones
+    let instructions = [
+        "ENTER or A: add a binding to the selected action",
+        "BACKSPACE or X: clear the selected action",
+        "Mouse sensitivity is in SETTINGS, stick sensitivity in input.cfg",
+    ];
+    let instructions_y = framebuffer.height.saturating_sub(90);
+    for (i, text) in instructions.iter().enumerate() {
+        framebuffer.draw_text(text, name_x, instructions_y + i as u32 * 22, 14, Color::SKYBLUE);
+    }
+}
+
+pub fn handle_controls_input(
+    game_manager: &mut GameManager,
+    input: &mut InputState,
+    window: &mut RaylibHandle,
+    audio_manager: &AudioManager,
+) {
+    //Esperando el nuevo enlace: el primer input del frame se asigna a la acción
+    if game_manager.capturing_binding {
+        if let Some(binding) = capture_binding(window) {
+            let action = Action::ALL[game_manager.controls_selected];
+            input.map.add_binding(action, binding);
+            game_manager.capturing_binding = false;
+            audio_manager.play_up_down_sound();
+        }
+        return;
+    }
+    
+    let rows = Action::ALL.len() + 2;
+    let gamepad_available = window.is_gamepad_available(0);
+    
+    //Las flechas y Enter siempre funcionan aquí, aunque se hayan desasignado las acciones del menú
+    if input.is_pressed(Action::MenuUp) || window.is_key_pressed(KeyboardKey::KEY_UP) {
+        audio_manager.play_up_down_sound();
+        game_manager.controls_selected = (game_manager.controls_selected + rows - 1) % rows;
+    }
+    
+    if input.is_pressed(Action::MenuDown) || window.is_key_pressed(KeyboardKey::KEY_DOWN) {
+        audio_manager.play_up_down_sound();
+        game_manager.controls_selected = (game_manager.controls_selected + 1) % rows;
+    }
+    
+    let clear = window.is_key_pressed(KeyboardKey::KEY_BACKSPACE) ||
+        window.is_key_pressed(KeyboardKey::KEY_DELETE) ||
+        (gamepad_available && window.is_gamepad_button_pressed(0, GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT));
+    if clear && let Some(action) = Action::ALL.get(game_manager.controls_selected) {
+        audio_manager.play_menu_sound();
+        input.map.clear_bindings(*action);
+    }
+    
+    let back = input.is_pressed(Action::Menu) || input.is_pressed(Action::Quit);
+    let confirm = input.is_pressed(Action::Confirm) || window.is_key_pressed(KeyboardKey::KEY_ENTER);
+    
+    if confirm && game_manager.controls_selected < Action::ALL.len() {
+        game_manager.capturing_binding = true;
+    } else if confirm && game_manager.controls_selected == Action::ALL.len() {
+        audio_manager.play_start_sound();
+        //La sensibilidad del mouse pertenece a los ajustes, no a los controles
+        let mouse_sensitivity = input.map.mouse_sensitivity;
+        input.map = InputMap::default_bindings();
+        input.map.mouse_sensitivity = mouse_sensitivity;
+    } else if confirm || back {
+        //Guardar siempre al salir para que los cambios se mantengan en la próxima sesión
+        if let Err(e) = input.map.save(INPUT_CONFIG) {
+            println!("No se pudo guardar {}: {}", INPUT_CONFIG, e);
+        }
+        audio_manager.play_menu_sound();
+        game_manager.reset_to_welcome();
+        game_manager.selected_option = CONTROLS_OPTION;
+    }
+}
+
+const PAUSE_OPTIONS: [&str; 4] = ["RESUME", "RESTART", "SETTINGS", "QUIT TO MENU"];
+
+//Menú de pausa dibujado sobre el último frame del juego
+pub fn draw_pause_menu(framebuffer: &mut Framebuffer, game_manager: &GameManager) {
+    let center_x = framebuffer.width / 2;
+    let center_y = framebuffer.height / 2;
+    
+    //Oscurecer toda la escena y dibujar el panel del menú encima
+    darken_scene(framebuffer);
+    
+    let panel_width = 400;
+    let panel_height = 260;
+    let panel_x = center_x.saturating_sub(panel_width / 2);
+    let panel_y = center_y.saturating_sub(panel_height / 2);
+    framebuffer.set_current_color(Color::new(0, 0, 0, 220));
+    for x in panel_x..(panel_x + panel_width).min(framebuffer.width) {
+        for y in panel_y..(panel_y + panel_height).min(framebuffer.height) {
+            framebuffer.set_pixel(x, y);
+        }
+    }
+    
+    let title_text = "PAUSED";
+    let title_char_width = 28;
+    let title_x = center_x.saturating_sub(title_text.len() as u32 * title_char_width / 2);
+    framebuffer.draw_text(title_text, title_x + 2, panel_y + 22, title_char_width, Color::BLACK);
+    framebuffer.draw_text(title_text, title_x, panel_y + 20, title_char_width, Color::new(255, 215, 0, 255));
+    
+    for (i, option_text) in PAUSE_OPTIONS.iter().enumerate() {
+        let option_char_width = 22;
+        let option_x = center_x.saturating_sub(option_text.len() as u32 * option_char_width / 2);
+        let option_y = panel_y + 90 + i as u32 * 40;
+        let selected = i == game_manager.selected_option;
+        let color = if selected { Color::YELLOW } else { Color::WHITE };
+        
+        framebuffer.draw_text(option_text, option_x, option_y, option_char_width, color);
+        if selected {
+            framebuffer.draw_text(">", option_x.saturating_sub(36), option_y, option_char_width, Color::YELLOW);
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub enum PauseAction {
+    None,
+    RestartLevel,
+}
+
+pub fn handle_pause_input(
+    game_manager: &mut GameManager,
+    input: &InputState,
+    audio_manager: &AudioManager,
+) -> PauseAction {
+    if input.is_pressed(Action::MenuUp) {
+        audio_manager.play_up_down_sound();
+        game_manager.selected_option = (game_manager.selected_option + PAUSE_OPTIONS.len() - 1) % PAUSE_OPTIONS.len();
+    }
+    
+    if input.is_pressed(Action::MenuDown) {
+        audio_manager.play_up_down_sound();
+        game_manager.selected_option = (game_manager.selected_option + 1) % PAUSE_OPTIONS.len();
+    }
+    
+    //La misma tecla que abre la pausa la cierra
+    if input.is_pressed(Action::Menu) {
+        audio_manager.play_menu_sound();
+        game_manager.resume();
+        return PauseAction::None;
+    }
+    
+    if !input.is_pressed(Action::Confirm) {
+        return PauseAction::None;
+    }
+    
+    match game_manager.selected_option {
+        0 => {
+            audio_manager.play_menu_sound();
+            game_manager.resume();
+            PauseAction::None
+        },
+        1 => {
+            audio_manager.play_start_sound();
+            game_manager.resume();
+            PauseAction::RestartLevel
+        },
+        2 => {
+            audio_manager.play_menu_sound();
+            game_manager.open_settings();
+            PauseAction::None
+        },
+        _ => {
+            audio_manager.play_menu_sound();
+            //Guardar también las partidas abandonadas, sirven para reproducir errores
+            if game_manager.playback.is_none() {
+                save_last_run(&game_manager.recording);
+            }
+            game_manager.reset_to_welcome();
+            PauseAction::None
+        },
+    }
+}
+
+pub fn draw_settings_screen(framebuffer: &mut Framebuffer, game_manager: &GameManager, settings: &Settings) {
+    //Fondo oscuro
+    framebuffer.set_current_color(Color::new(20, 20, 40, 255));
+    for x in 0..framebuffer.width {
+        for y in 0..framebuffer.height {
+            framebuffer.set_pixel(x, y);
+        }
+    }
+    
+    let title_text = "SETTINGS";
+    let title_char_width = 28;
+    let title_x = (framebuffer.width.saturating_sub(title_text.len() as u32 * title_char_width)) / 2;
+    framebuffer.draw_text(title_text, title_x + 2, 42, title_char_width, Color::BLACK);
+    framebuffer.draw_text(title_text, title_x, 40, title_char_width, Color::new(255, 215, 0, 255));
+    
+    let name_x = 120;
+    let value_x = 480;
+    for (i, row) in SettingsRow::ALL.iter().enumerate() {
+        let y = 130 + i as u32 * 48;
+        let selected = i == game_manager.settings_selected;
+        let color = if selected { Color::YELLOW } else { Color::WHITE };
+        
+        if selected {
+            framebuffer.draw_text(">", name_x - 40, y, 20, Color::YELLOW);
+        }
+        framebuffer.draw_text(row.get_name(), name_x, y, 20, color);
+        
+        let value = row.value_text(settings);
+        if !value.is_empty() {
+            framebuffer.draw_text(&format!("< {} >", value), value_x, y, 20, color);
+        }
+    }
+    
+    //Instrucciones
+    let instructions = [
+        "UP/DOWN: select   LEFT/RIGHT: change value",
+        "Changes apply immediately and are saved to settings.cfg",
+    ];
+    let instructions_y = framebuffer.height.saturating_sub(70);
+    for (i, text) in instructions.iter().enumerate() {
+        framebuffer.draw_text(text, name_x, instructions_y + i as u32 * 22, 14, Color::SKYBLUE);
+    }
+}
+
+//Devuelve true si cambió algún valor y hay que volver a aplicar los ajustes
+pub fn handle_settings_input(
+    game_manager: &mut GameManager,
+    settings: &mut Settings,
+    input: &InputState,
+    audio_manager: &AudioManager,
+) -> bool {
+    let rows = SettingsRow::ALL.len();
+    
+    if input.is_pressed(Action::MenuUp) {
+        audio_manager.play_up_down_sound();
+        game_manager.settings_selected = (game_manager.settings_selected + rows - 1) % rows;
+    }
+    
+    if input.is_pressed(Action::MenuDown) {
+        audio_manager.play_up_down_sound();
+        game_manager.settings_selected = (game_manager.settings_selected + 1) % rows;
+    }
+    
+    let row = SettingsRow::ALL[game_manager.settings_selected];
+    let direction = if input.is_pressed(Action::MenuLeft) {
+        -1
+    } else if input.is_pressed(Action::MenuRight) {
+        1
+    } else {
+        0
+    };
+    
+    if direction != 0 && row != SettingsRow::Back {
+        let previous = settings.clone();
+        row.adjust(settings, direction);
+        if *settings != previous {
+            audio_manager.play_up_down_sound();
+            return true;
+        }
+    }
+    
+    let back = input.is_pressed(Action::Menu) || input.is_pressed(Action::Quit);
+    let confirm = input.is_pressed(Action::Confirm) && row == SettingsRow::Back;
+    if back || confirm {
+        if let Err(e) = settings.save(SETTINGS_FILE) {
+            println!("No se pudo guardar {}: {}", SETTINGS_FILE, e);
+        }
+        audio_manager.play_menu_sound();
+        
+        //Volver a la pantalla desde la que se abrieron los ajustes
+        if game_manager.settings_return == GameState::Paused {
+            game_manager.state = GameState::Paused;
+            game_manager.selected_option = 2;
+        } else {
+            game_manager.reset_to_welcome();
+            game_manager.selected_option = SETTINGS_OPTION;
+        }
+    }
+    
+    false
 }
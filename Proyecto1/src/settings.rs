@@ -0,0 +1,238 @@
+use std::fs;
+
+pub const SETTINGS_FILE: &str = "settings.cfg";
+
+//Resoluciones disponibles en la pantalla de ajustes
+pub const RESOLUTIONS: [(u32, u32); 5] = [
+    (800, 600),
+    (1024, 768),
+    (1300, 900),
+    (1600, 900),
+    (1920, 1080),
+];
+
+//Cuánto del laberinto muestran el minimapa y la vista 2D
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapMode {
+    Full,     //Todo el laberinto, meta incluida
+    Explored, //Solo las celdas que el jugador ya vio
+    Off,      //Sin minimapa ni vista 2D
+}
+
+impl MapMode {
+    pub const ALL: [MapMode; 3] = [MapMode::Full, MapMode::Explored, MapMode::Off];
+
+    //Nombre en settings.cfg
+    pub fn get_name(&self) -> &str {
+        match self {
+            MapMode::Full => "full",
+            MapMode::Explored => "explored",
+            MapMode::Off => "off",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MapMode> {
+        MapMode::ALL.into_iter().find(|mode| mode.get_name() == name)
+    }
+}
+
+//Ajustes del jugador que se guardan en disco y se aplican al iniciar
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub volume: f32,            //Volumen general (0..1)
+    pub fov: f32,               //Campo de visión en grados
+    pub mouse_sensitivity: f32, //Radianes por píxel de movimiento del mouse
+    pub resolution: (u32, u32),
+    pub ghost: bool, //Mostrar el fantasma de la mejor partida del nivel
+    pub map: MapMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            volume: 1.0,
+            fov: 60.0,
+            mouse_sensitivity: 0.01,
+            resolution: (1300, 900),
+            ghost: true,
+            map: MapMode::Explored,
+        }
+    }
+}
+
+impl Settings {
+    //Archivo de líneas "clave = valor"; las claves que falten quedan por defecto
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut settings = Settings::default();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            if let Err(e) = settings.apply_line(line) {
+                println!("{}:{}: {}", path, line_number + 1, e);
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn load_or_default(path: &str) -> Self {
+        Settings::load(path).unwrap_or_default()
+    }
+
+    fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (key, value) = line.split_once('=').ok_or("expected 'name = value'")?;
+        let (key, value) = (key.trim(), value.trim());
+        let number = || value.parse::<f32>().map_err(|_| format!("invalid number '{}'", value));
+
+        match key {
+            "volume" => self.volume = number()?.clamp(0.0, 1.0),
+            "fov" => self.fov = number()?.clamp(MIN_FOV, MAX_FOV),
+            "mouse_sensitivity" => self.mouse_sensitivity = number()?.clamp(MIN_SENSITIVITY, MAX_SENSITIVITY),
+            "resolution" => {
+                let (w, h) = value.split_once('x').ok_or(format!("invalid resolution '{}'", value))?;
+                let w = w.trim().parse().map_err(|_| format!("invalid width '{}'", w))?;
+                let h = h.trim().parse().map_err(|_| format!("invalid height '{}'", h))?;
+                //Solo las que ofrece la pantalla de ajustes
+                if !RESOLUTIONS.contains(&(w, h)) {
+                    return Err(format!("unsupported resolution '{}'", value));
+                }
+                self.resolution = (w, h);
+            },
+            "ghost" => {
+                self.ghost = match value {
+                    "on" | "true" => true,
+                    "off" | "false" => false,
+                    _ => return Err(format!("invalid value '{}' for ghost (on/off)", value)),
+                };
+            },
+            "map" => {
+                self.map = MapMode::from_name(value)
+                    .ok_or(format!("invalid value '{}' for map (full/explored/off)", value))?;
+            },
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let contents = format!(
+            "#Ajustes del juego (se pueden editar desde el menú SETTINGS)\nvolume = {}\nfov = {}\nmouse_sensitivity = {}\nresolution = {}x{}\nghost = {}\nmap = {}\n",
+            self.volume, self.fov, self.mouse_sensitivity, self.resolution.0, self.resolution.1,
+            if self.ghost { "on" } else { "off" },
+            self.map.get_name(),
+        );
+        fs::write(path, contents)
+    }
+}
+
+pub const MIN_FOV: f32 = 40.0;
+pub const MAX_FOV: f32 = 120.0;
+pub const MIN_SENSITIVITY: f32 = 0.001;
+pub const MAX_SENSITIVITY: f32 = 0.05;
+
+//Filas de la pantalla de ajustes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsRow {
+    Volume,
+    Fov,
+    MouseSensitivity,
+    Resolution,
+    Ghost,
+    Map,
+    Back,
+}
+
+impl SettingsRow {
+    pub const ALL: [SettingsRow; 7] = [
+        SettingsRow::Volume,
+        SettingsRow::Fov,
+        SettingsRow::MouseSensitivity,
+        SettingsRow::Resolution,
+        SettingsRow::Ghost,
+        SettingsRow::Map,
+        SettingsRow::Back,
+    ];
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            SettingsRow::Volume => "Volume",
+            SettingsRow::Fov => "Field of view",
+            SettingsRow::MouseSensitivity => "Mouse sensitivity",
+            SettingsRow::Resolution => "Resolution",
+            SettingsRow::Ghost => "Ghost racer",
+            SettingsRow::Map => "Map",
+            SettingsRow::Back => "SAVE AND BACK",
+        }
+    }
+
+    pub fn value_text(&self, settings: &Settings) -> String {
+        match self {
+            SettingsRow::Volume => format!("{}%", (settings.volume * 100.0).round()),
+            SettingsRow::Fov => format!("{}", settings.fov.round()),
+            SettingsRow::MouseSensitivity => format!("{:.3}", settings.mouse_sensitivity),
+            SettingsRow::Resolution => format!("{}x{}", settings.resolution.0, settings.resolution.1),
+            SettingsRow::Ghost => if settings.ghost { "ON" } else { "OFF" }.to_string(),
+            SettingsRow::Map => settings.map.get_name().to_uppercase(),
+            SettingsRow::Back => String::new(),
+        }
+    }
+
+    //Cambia el valor un paso hacia arriba (+1) o hacia abajo (-1)
+    pub fn adjust(&self, settings: &mut Settings, direction: i32) {
+        let step = direction as f32;
+        match self {
+            SettingsRow::Volume => settings.volume = (settings.volume + 0.1 * step).clamp(0.0, 1.0),
+            SettingsRow::Fov => settings.fov = (settings.fov + 5.0 * step).clamp(MIN_FOV, MAX_FOV),
+            SettingsRow::MouseSensitivity => {
+                settings.mouse_sensitivity = (settings.mouse_sensitivity + 0.001 * step).clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+            },
+            SettingsRow::Resolution => {
+                let current = RESOLUTIONS.iter().position(|&r| r == settings.resolution).unwrap_or(2);
+                let next = (current as i32 + direction).clamp(0, RESOLUTIONS.len() as i32 - 1);
+                settings.resolution = RESOLUTIONS[next as usize];
+            },
+            SettingsRow::Ghost => settings.ghost = !settings.ghost,
+            SettingsRow::Map => {
+                let current = MapMode::ALL.iter().position(|&mode| mode == settings.map).unwrap_or(0);
+                let next = (current as i32 + direction).clamp(0, MapMode::ALL.len() as i32 - 1);
+                settings.map = MapMode::ALL[next as usize];
+            },
+            SettingsRow::Back => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_must_be_listed() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.apply_line("resolution = 1600x900"), Ok(()));
+        assert_eq!(settings.resolution, (1600, 900));
+
+        for value in ["0x0", "1300x0", "1234x567"] {
+            let line = format!("resolution = {}", value);
+            assert_eq!(settings.apply_line(&line), Err(format!("unsupported resolution '{}'", value)));
+        }
+        //Un valor rechazado no cambia el ajuste
+        assert_eq!(settings.resolution, (1600, 900));
+    }
+
+    #[test]
+    fn mouse_sensitivity_is_clamped() {
+        //Mismo rango que en la pantalla de ajustes
+        let mut settings = Settings::default();
+        for (value, expected) in [("0.02", 0.02), ("0", MIN_SENSITIVITY), ("-1", MIN_SENSITIVITY), ("3", MAX_SENSITIVITY)] {
+            settings.apply_line(&format!("mouse_sensitivity = {}", value)).unwrap();
+            assert_eq!(settings.mouse_sensitivity, expected);
+        }
+    }
+}
@@ -1,12 +1,21 @@
 use raylib::prelude::*;
 use std::collections::HashMap;
 use crate::maze::Maze;
+use crate::level::{SpriteSpawnRules, is_walkable};
 use crate::player::Player;
 use crate::framebuffer::Framebuffer;
+use crate::caster::{cast_ray_from, projection_plane_distance};
+use crate::enemy::{Enemy, SIGHT_RANGE, CATCH_DISTANCE};
+use crate::pathfinding::{Cell, distance_field, cell_center};
+use crate::doors::Doors;
+use crate::items::ItemKind;
 use image;
 use std::fs::File;
 use std::io::BufReader;
 
+//Altura de los sprites en el mundo, como fracción de la altura de una pared
+const SPRITE_HEIGHT: f32 = 0.6;
+
 #[derive(Clone)]
 pub struct AnimatedSprite {
     pub position: Vector2,
@@ -15,13 +24,86 @@ pub struct AnimatedSprite {
     pub frame_timer: f32,
     pub scale: f32,
     pub visible: bool,
+    pub opacity: f32, //1.0 opaco; el fantasma se dibuja translúcido
+    pub enemy: Option<Enemy>, //Los enemigos se mueven y persiguen al jugador
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum SpriteType {
     Naruto,
+    Enemy, //Mismos frames que Naruto teñidos de rojo
+    Item(ItemKind), //Objeto para recoger, dibujado a partir de un patrón de texto
 }
 
+//Objetos de 12x12 píxeles: '#' color del objeto, 'o' sombra, el resto transparente
+const KEY_PATTERN: [&str; 12] = [
+    "............",
+    "............",
+    "..###.......",
+    ".#ooo#......",
+    ".#o.o#######",
+    ".#ooo#..#.#.",
+    "..###...#.#.",
+    "............",
+    "............",
+    "............",
+    "............",
+    "............",
+];
+const SCROLL_PATTERN: [&str; 12] = [
+    "............",
+    "............",
+    ".oooooooooo.",
+    "..########..",
+    "..#oooooo#..",
+    "..########..",
+    "..#ooooo##..",
+    "..########..",
+    "..#oooo###..",
+    "..########..",
+    ".oooooooooo.",
+    "............",
+];
+const HOURGLASS_PATTERN: [&str; 12] = [
+    "............",
+    "..oooooooo..",
+    "...######...",
+    "...######...",
+    "....####....",
+    ".....##.....",
+    ".....##.....",
+    "....#..#....",
+    "...#....#...",
+    "...######...",
+    "..oooooooo..",
+    "............",
+];
+const MAP_PATTERN: [&str; 12] = [
+    "............",
+    "............",
+    ".###o###o##.",
+    ".###o###o##.",
+    ".#o#o###o##.",
+    ".##oo###o##.",
+    ".###o#o#o##.",
+    ".###o##oo##.",
+    ".###o###o##.",
+    ".###o###o##.",
+    "............",
+    "............",
+];
+//Escala de los objetos respecto a los demás sprites
+const ITEM_SCALE: f32 = 0.5;
+//Distancia a la que el jugador recoge un objeto
+const PICKUP_DISTANCE: f32 = 40.0;
+
+//Tinte de los enemigos sobre los frames de Naruto
+const ENEMY_TINT: Color = Color::new(255, 90, 90, 255);
+//Distancia mínima, en pasos desde el spawn del jugador, a la que aparece un enemigo aleatorio
+const ENEMY_SPAWN_DISTANCE: usize = 8;
+//Largo máximo, en pasos, de cada tramo de una ronda aleatoria
+const ENEMY_ROUTE_LENGTH: usize = 10;
+
 //Estructura para almacenar datos de píxeles de una textura
 #[derive(Clone)]
 pub struct SpritePixelData {
@@ -30,8 +112,12 @@ pub struct SpritePixelData {
     pub pixels: Vec<Color>,
 }
 
+//Opacidad del fantasma de la mejor partida
+const GHOST_OPACITY: f32 = 0.4;
+
 pub struct SpriteManager {
     pub sprites: Vec<AnimatedSprite>,
+    ghost: Option<AnimatedSprite>, //Fantasma de la mejor partida; no lo borra el spawn
     sprite_pixel_data: HashMap<SpriteType, Vec<SpritePixelData>>,
     frame_duration: f32,
 }
@@ -40,13 +126,30 @@ impl SpriteManager {
     pub fn new() -> Self {
         SpriteManager {
             sprites: Vec::new(),
+            ghost: None,
             sprite_pixel_data: HashMap::new(),
             frame_duration: 0.08, 
         }
     }
 
     pub fn load_sprite_textures(&mut self, _rl: &mut RaylibHandle, _thread: &RaylibThread) {
+        self.load_sprite_frames();
+    }
+    
+    //Los frames se decodifican en CPU, así que también sirven sin ventana
+    pub fn load_sprite_frames(&mut self) {
         self.load_gif_frames(SpriteType::Naruto, "assets/img/naruto.gif");
+        
+        //Los enemigos reutilizan la animación de Naruto con otro color
+        if let Some(frames) = self.sprite_pixel_data.get(&SpriteType::Naruto) {
+            let tinted = frames.iter().map(|frame| tint_frame(frame, ENEMY_TINT)).collect();
+            self.sprite_pixel_data.insert(SpriteType::Enemy, tinted);
+        }
+        
+        for kind in ItemKind::ALL {
+            let (pattern, color) = item_pattern(kind);
+            self.sprite_pixel_data.insert(SpriteType::Item(kind), vec![pattern_frame(pattern, color)]);
+        }
     }
     
     fn load_gif_frames(&mut self, sprite_type: SpriteType, path: &str) {
@@ -175,9 +278,23 @@ impl SpriteManager {
     
     
     pub fn spawn_sprites_in_maze(&mut self, maze: &Maze, block_size: usize) {
+        self.spawn_sprites_with_rules(maze, block_size, &SpriteSpawnRules::default());
+    }
+    
+    pub fn spawn_sprites_with_rules(&mut self, maze: &Maze, block_size: usize, rules: &SpriteSpawnRules) {
         self.sprites.clear();
         
-        let target_sprites = 8;
+        //Sprites en posiciones fijas declaradas por el nivel
+        for &(x, y) in &rules.positions {
+            let position = Vector2::new(
+                x as f32 * block_size as f32 + block_size as f32 / 2.0,
+                y as f32 * block_size as f32 + block_size as f32 / 2.0,
+            );
+            let sprite = self.new_sprite(position);
+            self.sprites.push(sprite);
+        }
+        
+        let target_sprites = rules.count.max(self.sprites.len());
         let maze_width = if !maze.is_empty() { maze[0].len() } else { 0 };
         let maze_height = maze.len();
         
@@ -236,8 +353,8 @@ impl SpriteManager {
         }
         
         //Spawning con separación mínima
-        let mut spawned = 0;
-        let min_distance_squared = (block_size * 3) * (block_size * 3); //Distancia mínima entre sprites
+        let mut spawned = self.sprites.len();
+        let min_distance_squared = (block_size * rules.min_spacing) * (block_size * rules.min_spacing); //Distancia mínima entre sprites
         
         for &(x, y) in &valid_positions {
             if spawned >= target_sprites {
@@ -257,14 +374,7 @@ impl SpriteManager {
             });
             
             if !too_close {
-                let sprite = AnimatedSprite {
-                    position: new_pos,
-                    sprite_type: SpriteType::Naruto, 
-                    current_frame: fastrand::usize(0..4), //Frame inicial aleatorio
-                    frame_timer: fastrand::f32() * self.frame_duration, //Timer inicial aleatorio
-                    scale: 1.0,
-                    visible: true,
-                };
+                let sprite = self.new_sprite(new_pos);
                 
                 self.sprites.push(sprite);
                 spawned += 1;
@@ -277,7 +387,8 @@ impl SpriteManager {
         //Si no hay suficientes sprites, ser menos estricto con la distancia
         if spawned < target_sprites {
             println!("Solo {} sprites spawneados, intentando completar con distancia menor...", spawned);
-            let reduced_distance_squared = (block_size * 2) * (block_size * 2);
+            let reduced_spacing = rules.min_spacing.saturating_sub(1);
+            let reduced_distance_squared = (block_size * reduced_spacing) * (block_size * reduced_spacing);
             
             for &(x, y) in &valid_positions {
                 if spawned >= target_sprites {
@@ -296,14 +407,7 @@ impl SpriteManager {
                 });
                 
                 if !too_close {
-                    let sprite = AnimatedSprite {
-                        position: new_pos,
-                        sprite_type: SpriteType::Naruto,
-                        current_frame: fastrand::usize(0..4),
-                        frame_timer: fastrand::f32() * self.frame_duration,
-                        scale: 1.0,
-                        visible: true,
-                    };
+                    let sprite = self.new_sprite(new_pos);
                     
                     self.sprites.push(sprite);
                     spawned += 1;
@@ -320,11 +424,179 @@ impl SpriteManager {
         }
     }
 
+    fn new_sprite(&self, position: Vector2) -> AnimatedSprite {
+        AnimatedSprite {
+            position,
+            sprite_type: SpriteType::Naruto, 
+            current_frame: fastrand::usize(0..4), //Frame inicial aleatorio
+            frame_timer: fastrand::f32() * self.frame_duration, //Timer inicial aleatorio
+            scale: 1.0,
+            visible: true,
+            opacity: 1.0,
+            enemy: None,
+        }
+    }
+    
+    //Enemigos del nivel: primero las rondas declaradas y luego los aleatorios, lejos del jugador
+    pub fn spawn_enemies(&mut self, maze: &Maze, block_size: usize, rules: &SpriteSpawnRules, player_spawn: (usize, usize)) {
+        for route in &rules.routes {
+            self.push_enemy(route.clone(), block_size);
+        }
+        
+        if rules.enemies == 0 {
+            return;
+        }
+        
+        let mut candidates: Vec<(usize, usize)> = distance_field(maze, &[player_spawn], is_walkable).cells()
+            .filter(|&((x, y), distance)| maze[y][x] == ' ' && distance >= ENEMY_SPAWN_DISTANCE)
+            .map(|(cell, _)| cell)
+            .filter(|cell| !rules.routes.iter().any(|route| route.first() == Some(cell)))
+            .collect();
+        fastrand::shuffle(&mut candidates);
+        
+        for &start in candidates.iter().take(rules.enemies) {
+            //Ronda de ida y vuelta entre el punto de aparición y dos celdas cercanas
+            let mut nearby: Vec<(usize, usize)> = distance_field(maze, &[start], is_walkable).cells()
+                .filter(|&(_, distance)| (3..=ENEMY_ROUTE_LENGTH).contains(&distance))
+                .map(|(cell, _)| cell)
+                .collect();
+            fastrand::shuffle(&mut nearby);
+            
+            let mut route = vec![start];
+            route.extend(nearby.into_iter().take(2));
+            println!("Enemigo en ({}, {}) con una ronda de {} puntos", start.0, start.1, route.len());
+            self.push_enemy(route, block_size);
+        }
+        
+        if candidates.len() < rules.enemies {
+            println!("Advertencia: Solo se pudieron colocar {} de {} enemigos", candidates.len(), rules.enemies);
+        }
+    }
+    
+    //Objetos del nivel: los fijos en su celda y los aleatorios en celdas libres alcanzables
+    //sin pasar por puertas, así una llave nunca queda detrás de la puerta que abre
+    pub fn spawn_items(&mut self, maze: &Maze, block_size: usize, placed: &[(ItemKind, Cell)], rules: &SpriteSpawnRules, player_spawn: Cell) {
+        for &(kind, cell) in placed {
+            self.push_item(kind, cell, block_size);
+        }
+        
+        let wanted: usize = rules.items.iter().map(|&(_, count)| count).sum();
+        if wanted == 0 {
+            return;
+        }
+        
+        let mut candidates: Vec<Cell> = distance_field(maze, &[player_spawn], is_walkable).cells()
+            .filter(|&((x, y), distance)| maze[y][x] == ' ' && distance > 0)
+            .map(|(cell, _)| cell)
+            .filter(|cell| !placed.iter().any(|(_, other)| other == cell))
+            .collect();
+        fastrand::shuffle(&mut candidates);
+        
+        let mut free = candidates.into_iter();
+        for &(kind, count) in &rules.items {
+            for _ in 0..count {
+                match free.next() {
+                    Some(cell) => self.push_item(kind, cell, block_size),
+                    None => {
+                        println!("Advertencia: No hay celdas libres para más objetos ({})", kind.get_name());
+                        return;
+                    }
+                }
+            }
+        }
+    }
+    
+    fn push_item(&mut self, kind: ItemKind, cell: Cell, block_size: usize) {
+        let mut sprite = self.new_sprite(cell_center(cell, block_size));
+        sprite.sprite_type = SpriteType::Item(kind);
+        sprite.scale = ITEM_SCALE;
+        self.sprites.push(sprite);
+    }
+    
+    //Quita los objetos que el jugador tiene al alcance y devuelve cuáles recogió
+    pub fn collect_items(&mut self, player_pos: Vector2) -> Vec<ItemKind> {
+        let mut collected = Vec::new();
+        self.sprites.retain(|sprite| match sprite.sprite_type {
+            SpriteType::Item(kind) if (sprite.position - player_pos).length() < PICKUP_DISTANCE => {
+                collected.push(kind);
+                false
+            },
+            _ => true,
+        });
+        collected
+    }
+    
+    fn push_enemy(&mut self, route: Vec<(usize, usize)>, block_size: usize) {
+        let Some(&start) = route.first() else {
+            return;
+        };
+        let mut sprite = self.new_sprite(cell_center(start, block_size));
+        sprite.sprite_type = SpriteType::Enemy;
+        sprite.enemy = Some(Enemy::new(route));
+        self.sprites.push(sprite);
+    }
+    
+    //Mueve a los enemigos un paso; devuelve si alguno alcanzó al jugador
+    pub fn update_enemies(&mut self, dt: f32, maze: &Maze, doors: &Doors, player_pos: Vector2, block_size: usize) -> bool {
+        let sight_range = SIGHT_RANGE * block_size as f32;
+        let mut caught = false;
+        
+        for i in 0..self.sprites.len() {
+            let position = self.sprites[i].position;
+            if self.sprites[i].enemy.is_none() {
+                continue;
+            }
+            
+            let sees_player = (player_pos - position).length() <= sight_range
+                && !self.has_wall_between(position, player_pos, maze, doors, block_size);
+            
+            let sprite = &mut self.sprites[i];
+            if let Some(ref mut enemy) = sprite.enemy {
+                enemy.update(&mut sprite.position, player_pos, sees_player, maze, block_size, dt);
+                
+                if (player_pos - sprite.position).length() < CATCH_DISTANCE && enemy.can_catch() {
+                    enemy.on_catch(sprite.position, maze, block_size);
+                    caught = true;
+                }
+            }
+        }
+        
+        caught
+    }
+    
+    //Posición y dirección de cada enemigo, para los mapas
+    pub fn enemy_markers(&self) -> Vec<(Vector2, f32)> {
+        self.sprites.iter()
+            .filter_map(|sprite| sprite.enemy.as_ref().map(|enemy| (sprite.position, enemy.heading)))
+            .collect()
+    }
+    
+    //Mover el fantasma (o quitarlo con None); no usa fastrand para no alterar el spawn de sprites
+    pub fn set_ghost(&mut self, position: Option<Vector2>) {
+        match (position, &mut self.ghost) {
+            (None, _) => self.ghost = None,
+            (Some(position), Some(ghost)) => ghost.position = position,
+            (Some(position), None) => {
+                self.ghost = Some(AnimatedSprite {
+                    position,
+                    sprite_type: SpriteType::Naruto,
+                    current_frame: 0,
+                    frame_timer: 0.0,
+                    scale: 1.0,
+                    visible: true,
+                    opacity: GHOST_OPACITY,
+                    enemy: None,
+                });
+            },
+        }
+    }
+
     pub fn update(&mut self, dt: f32) {
-        for sprite in &mut self.sprites {
+        for sprite in self.sprites.iter_mut().chain(self.ghost.as_mut()) {
             sprite.frame_timer += dt;
             if sprite.frame_timer >= self.frame_duration {
-                sprite.frame_timer = 0.0;
+                //Conservar el sobrante para que la animación no dependa del dt
+                sprite.frame_timer -= self.frame_duration;
                 
                 //Obtener el número de frames disponibles para este sprite
                 let num_frames = if let Some(pixel_frames) = self.sprite_pixel_data.get(&sprite.sprite_type) {
@@ -338,139 +610,177 @@ impl SpriteManager {
         }
     }
 
-    fn has_wall_between(&self, from: Vector2, to: Vector2, maze: &Maze, block_size: usize) -> bool {
+    fn has_wall_between(&self, from: Vector2, to: Vector2, maze: &Maze, doors: &Doors, block_size: usize) -> bool {
         let dx = to.x - from.x;
         let dy = to.y - from.y;
         let distance = (dx * dx + dy * dy).sqrt();
-        let steps = (distance / 5.0).ceil() as i32;
         
-        if steps == 0 {
+        if distance < 1.0 {
             return false;
         }
         
-        let step_x = dx / steps as f32;
-        let step_y = dy / steps as f32;
-        
-        let maze_width = if !maze.is_empty() { maze[0].len() } else { 0 };
-        let maze_height = maze.len();
+        //Hay pared si el rayo DDA choca antes de llegar al destino
+        let intersect = cast_ray_from(maze, doors, from, dy.atan2(dx), block_size);
+        intersect.distance < distance
+    }
+
+    pub fn render_sprites_3d(&self, framebuffer: &mut Framebuffer, player: &Player, block_size: usize) {
+        let distance_to_projection_plane = projection_plane_distance(player.fov, framebuffer.width);
+        let dir_x = player.a.cos();
+        let dir_y = player.a.sin();
         
-        for i in 1..steps {
-            let check_x = from.x + step_x * i as f32;
-            let check_y = from.y + step_y * i as f32;
-            
-            let maze_x = (check_x / block_size as f32) as usize;
-            let maze_y = (check_y / block_size as f32) as usize;
-            
-            if maze_x < maze_width && maze_y < maze_height {
-                if maze[maze_y][maze_x] != ' ' {
-                    return true;
+        //Transformar cada sprite al espacio de cámara: profundidad y desplazamiento lateral
+        let mut visible_sprites: Vec<(&AnimatedSprite, f32, f32)> = self.sprites.iter()
+            .chain(self.ghost.as_ref())
+            .filter(|sprite| sprite.visible)
+            .filter_map(|sprite| {
+                let dx = sprite.position.x - player.pos.x;
+                let dy = sprite.position.y - player.pos.y;
+                let depth = dx * dir_x + dy * dir_y;
+                let lateral = -dx * dir_y + dy * dir_x;
+                
+                //Descartar sprites detrás de la cámara
+                if depth < 1.0 {
+                    None
+                } else {
+                    Some((sprite, depth, lateral))
                 }
-            }
-        }
+            })
+            .collect();
+        
+        //Dibujar de atrás hacia adelante para que los sprites cercanos tapen a los lejanos
+        visible_sprites.sort_by(|a, b| b.1.total_cmp(&a.1));
         
-        false
+        for (sprite, depth, lateral) in visible_sprites {
+            self.render_sprite(framebuffer, sprite, depth, lateral, distance_to_projection_plane, block_size);
+        }
     }
-
-    pub fn render_sprites_3d(&self, framebuffer: &mut Framebuffer, player: &Player, maze: &Maze, block_size: usize) {
-        for sprite in &self.sprites {
-            if !sprite.visible {
-                continue;
-            }
-            
-            let dx = sprite.position.x - player.pos.x;
-            let dy = sprite.position.y - player.pos.y;
-            let distance = (dx * dx + dy * dy).sqrt();
-            
-            if distance > 300.0 {
-                continue;
-            }
-            
-            if self.has_wall_between(player.pos, sprite.position, maze, block_size) {
-                continue;
-            }
-            
-            let sprite_angle = dy.atan2(dx);
-            let mut angle_diff = sprite_angle - player.a;
-            
-            while angle_diff > std::f32::consts::PI {
-                angle_diff -= 2.0 * std::f32::consts::PI;
-            }
-            while angle_diff < -std::f32::consts::PI {
-                angle_diff += 2.0 * std::f32::consts::PI;
-            }
-            
-            if angle_diff.abs() > player.fov / 2.0 {
+    
+    fn render_sprite(
+        &self,
+        framebuffer: &mut Framebuffer,
+        sprite: &AnimatedSprite,
+        depth: f32,
+        lateral: f32,
+        distance_to_projection_plane: f32,
+        block_size: usize,
+    ) {
+        let hh = framebuffer.height as f32 / 2.0;
+        let block = block_size as f32;
+        
+        //Más allá del plano lejano de la niebla el sprite no se ve
+        let fog = framebuffer.fog;
+        if fog.is_some_and(|fog| depth / block >= fog.far_plane()) {
+            return;
+        }
+        let fog_amount = fog.map_or(0.0, |fog| fog.amount(depth / block));
+        
+        //Proyección con el mismo plano que las paredes
+        let screen_x = framebuffer.width as f32 / 2.0 + lateral / depth * distance_to_projection_plane;
+        let sprite_height = (block * SPRITE_HEIGHT * sprite.scale / depth) * distance_to_projection_plane;
+        let sprite_width = sprite_height;
+        
+        //El sprite se apoya en el piso (los ojos están a media altura de la pared)
+        let floor_y = hh + (block / 2.0 / depth) * distance_to_projection_plane;
+        let top = floor_y - sprite_height;
+        let left = screen_x - sprite_width / 2.0;
+        
+        if left + sprite_width <= 0.0 || left >= framebuffer.width as f32 {
+            return;
+        }
+        
+        let start_x = left.max(0.0) as u32;
+        let end_x = (left + sprite_width).min(framebuffer.width as f32) as u32;
+        let start_y = top.max(0.0) as u32;
+        let end_y = floor_y.min(framebuffer.height as f32) as u32;
+        
+        //Usar el frame actual de la animación si hay datos de píxeles
+        let pixel_data = self.sprite_pixel_data.get(&sprite.sprite_type)
+            .filter(|frames| !frames.is_empty())
+            .map(|frames| &frames[sprite.current_frame.min(frames.len() - 1)])
+            .filter(|data| data.width > 0 && data.height > 0);
+        
+        for column in start_x..end_x {
+            //Prueba de profundidad por columna contra las paredes
+            if depth >= framebuffer.z_buffer[column as usize] {
                 continue;
             }
             
-            let screen_x = ((angle_diff / player.fov) + 0.5) * framebuffer.width as f32;
-            let sprite_height = (framebuffer.height as f32 / distance) * 50.0 * sprite.scale;
-            let sprite_width = sprite_height;
+            let tx = (column as f32 + 0.5 - left) / sprite_width;
             
-            if screen_x + sprite_width / 2.0 > 0.0 && screen_x - sprite_width / 2.0 < framebuffer.width as f32 {
-                //Usar los datos de píxeles reales para el renderizado 3D 
-                if let Some(pixel_frames) = self.sprite_pixel_data.get(&sprite.sprite_type) {
-                    if !pixel_frames.is_empty() {
-                        //Usar el frame actual de la animación
-                        let frame_index = sprite.current_frame.min(pixel_frames.len() - 1);
-                        let pixel_data = &pixel_frames[frame_index];
-                        
-                        let start_x = (screen_x - sprite_width / 2.0).max(0.0) as u32;
-                        let end_x = (screen_x + sprite_width / 2.0).min(framebuffer.width as f32) as u32;
-                        let start_y = ((framebuffer.height as f32 / 2.0) - sprite_height / 2.0).max(0.0) as u32;
-                        let end_y = ((framebuffer.height as f32 / 2.0) + sprite_height / 2.0).min(framebuffer.height as f32) as u32;
-                        
-                        //Renderizar usando los píxeles reales del sprite
-                        for screen_y in start_y..end_y {
-                            for screen_x_pos in start_x..end_x {
-                                if screen_x_pos < framebuffer.width && screen_y < framebuffer.height {
-                                    //Mapear coordenadas de pantalla a coordenadas de textura
-                                    let tex_x = ((screen_x_pos - start_x) * pixel_data.width / (end_x - start_x).max(1)) as usize;
-                                    let tex_y = ((screen_y - start_y) * pixel_data.height / (end_y - start_y).max(1)) as usize;
-                                    
-                                    if tex_x < pixel_data.width as usize && tex_y < pixel_data.height as usize {
-                                        let pixel_index = tex_y * pixel_data.width as usize + tex_x;
-                                        if pixel_index < pixel_data.pixels.len() {
-                                            let pixel_color = pixel_data.pixels[pixel_index];
-                                            
-                                            //Solo dibujar píxeles no transparentes
-                                            if pixel_color.a > 128 { 
-                                                framebuffer.set_current_color(pixel_color);
-                                                framebuffer.set_pixel(screen_x_pos, screen_y);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+            for row in start_y..end_y {
+                let ty = (row as f32 + 0.5 - top) / sprite_height;
+                
+                let pixel_color = match pixel_data {
+                    Some(pixel_data) => {
+                        //Mapear coordenadas de pantalla a coordenadas de textura
+                        let tex_x = ((tx * pixel_data.width as f32) as usize).min(pixel_data.width as usize - 1);
+                        let tex_y = ((ty * pixel_data.height as f32) as usize).min(pixel_data.height as usize - 1);
+                        let pixel_index = tex_y * pixel_data.width as usize + tex_x;
+                        match pixel_data.pixels.get(pixel_index) {
+                            Some(&color) => color,
+                            None => continue,
                         }
-                    } else {
-                        //Fallback: usar color sólido si no hay datos de píxeles
-                        self.render_3d_fallback(framebuffer, sprite, screen_x, sprite_width, sprite_height);
-                    }
-                } else {
-                    //Fallback si no hay sprite data
-                    self.render_3d_fallback(framebuffer, sprite, screen_x, sprite_width, sprite_height);
+                    },
+                    //Fallback: usar color sólido si no hay datos de píxeles
+                    None => Color::new(255, 165, 0, 255),
+                };
+                
+                let pixel_color = match fog {
+                    Some(fog) => fog.mix(pixel_color, fog_amount),
+                    None => pixel_color,
+                };
+                
+                //Solo dibujar píxeles no transparentes
+                if pixel_color.a > 128 && sprite.opacity < 1.0 {
+                    let alpha = (sprite.opacity * 255.0) as u8;
+                    framebuffer.blend_pixel(column, row, Color::new(pixel_color.r, pixel_color.g, pixel_color.b, alpha));
+                } else if pixel_color.a > 128 {
+                    framebuffer.set_current_color(pixel_color);
+                    framebuffer.set_pixel(column, row);
                 }
             }
         }
     }
+}
+
+//Multiplica cada canal por el tinte, conservando la transparencia
+fn tint_frame(frame: &SpritePixelData, tint: Color) -> SpritePixelData {
+    let channel = |value: u8, tint: u8| (value as u16 * tint as u16 / 255) as u8;
+    SpritePixelData {
+        width: frame.width,
+        height: frame.height,
+        pixels: frame.pixels.iter()
+            .map(|p| Color::new(channel(p.r, tint.r), channel(p.g, tint.g), channel(p.b, tint.b), p.a))
+            .collect(),
+    }
+}
+
+//Dibujo y color de cada objeto
+fn item_pattern(kind: ItemKind) -> (&'static [&'static str], Color) {
+    match kind {
+        ItemKind::Key => (&KEY_PATTERN, Color::new(240, 200, 40, 255)),
+        ItemKind::Scroll => (&SCROLL_PATTERN, Color::new(235, 215, 165, 255)),
+        ItemKind::TimeBonus => (&HOURGLASS_PATTERN, Color::new(90, 200, 240, 255)),
+        ItemKind::MapReveal => (&MAP_PATTERN, Color::new(130, 205, 95, 255)),
+    }
+}
+
+//Frame a partir de un dibujo de texto, para sprites sin imagen
+fn pattern_frame(pattern: &[&str], color: Color) -> SpritePixelData {
+    let width = pattern.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let height = pattern.len() as u32;
+    let mut pixels = Vec::with_capacity((width * height) as usize);
     
-    fn render_3d_fallback(&self, framebuffer: &mut Framebuffer, _sprite: &AnimatedSprite, screen_x: f32, sprite_width: f32, sprite_height: f32) {
-        let color = Color::new(255, 165, 0, 255); 
-        
-        framebuffer.set_current_color(color);
-        
-        let start_x = (screen_x - sprite_width / 2.0).max(0.0) as u32;
-        let end_x = (screen_x + sprite_width / 2.0).min(framebuffer.width as f32) as u32;
-        let start_y = ((framebuffer.height as f32 / 2.0) - sprite_height / 2.0).max(0.0) as u32;
-        let end_y = ((framebuffer.height as f32 / 2.0) + sprite_height / 2.0).min(framebuffer.height as f32) as u32;
-        
-        for x in start_x..end_x {
-            for y in start_y..end_y {
-                if x < framebuffer.width && y < framebuffer.height {
-                    framebuffer.set_pixel(x, y);
-                }
-            }
+    for row in pattern {
+        for x in 0..width as usize {
+            pixels.push(match row.as_bytes().get(x) {
+                Some(b'#') => color,
+                Some(b'o') => Color::new(color.r / 3 * 2, color.g / 3 * 2, color.b / 3 * 2, 255),
+                _ => Color::new(0, 0, 0, 0),
+            });
         }
     }
-}
\ No newline at end of file
+    
+    SpritePixelData { width, height, pixels }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+
+pub const LEADERBOARD_FILE: &str = "leaderboard.txt";
+//Cantidad de tiempos que se muestran por nivel
+pub const LEADERBOARD_SIZE: usize = 10;
+pub const INITIALS_LENGTH: usize = 3;
+
+//Resultados de la partida en curso
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunStats {
+    pub elapsed: f32,  //Segundos simulados desde que empezó la partida
+    pub distance: f32, //Distancia recorrida en celdas
+    pub resets: u32,   //Veces que se reinició el nivel sin terminar la partida
+    pub catches: u32,  //Veces que un enemigo alcanzó al jugador
+    pub ticks: usize,  //Pasos de simulación
+    pub explored: f32, //Porcentaje del nivel descubierto, se calcula al ganar
+}
+
+impl RunStats {
+    //Se llama en cada paso fijo de la simulación
+    pub fn advance(&mut self, dt: f32, distance: f32) {
+        self.elapsed += dt;
+        self.distance += distance;
+        self.ticks += 1;
+    }
+}
+
+//Tiempo como m:ss.cc
+pub fn format_time(seconds: f32) -> String {
+    let centiseconds = (seconds * 100.0).round() as u32;
+    format!("{}:{:02}.{:02}", centiseconds / 6000, centiseconds / 100 % 60, centiseconds % 100)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub level: String, //Clave del nivel (dificultad o semilla con sus opciones)
+    pub initials: String,
+    pub time: f32,
+    pub distance: f32,
+    pub resets: u32,
+}
+
+impl Record {
+    //Una línea por récord: nivel iniciales tiempo distancia reinicios
+    fn parse(line: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 5 {
+            return Err(format!("expected 5 fields, found {}", parts.len()));
+        }
+
+        Ok(Record {
+            level: parts[0].to_string(),
+            initials: parts[1].to_string(),
+            time: parts[2].parse().map_err(|_| format!("invalid time '{}'", parts[2]))?,
+            distance: parts[3].parse().map_err(|_| format!("invalid distance '{}'", parts[3]))?,
+            resets: parts[4].parse().map_err(|_| format!("invalid resets '{}'", parts[4]))?,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("{} {} {:.3} {:.2} {}", self.level, self.initials, self.time, self.distance, self.resets)
+    }
+}
+
+//Todos los récords locales; se guardan completos y se ordenan al consultarlos
+#[derive(Debug, Clone, Default)]
+pub struct Leaderboard {
+    records: Vec<Record>,
+}
+
+impl Leaderboard {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut leaderboard = Leaderboard::default();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match Record::parse(line) {
+                Ok(record) => leaderboard.records.push(record),
+                Err(e) => println!("{}:{}: {}", path, line_number + 1, e),
+            }
+        }
+
+        Ok(leaderboard)
+    }
+
+    pub fn load_or_default(path: &str) -> Self {
+        Leaderboard::load(path).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let mut contents = String::from("#nivel iniciales tiempo distancia reinicios\n");
+        for record in &self.records {
+            contents.push_str(&record.describe());
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn add(&mut self, record: Record) {
+        self.records.push(record);
+    }
+
+    //Mejores tiempos del nivel; a igual tiempo gana el récord más antiguo
+    pub fn top(&self, level: &str) -> Vec<&Record> {
+        let mut records: Vec<&Record> = self.records.iter().filter(|r| r.level == level).collect();
+        records.sort_by(|a, b| a.time.total_cmp(&b.time));
+        records.truncate(LEADERBOARD_SIZE);
+        records
+    }
+
+    pub fn personal_best(&self, level: &str, initials: &str) -> Option<f32> {
+        self.records.iter()
+            .filter(|r| r.level == level && r.initials == initials)
+            .map(|r| r.time)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    //Iniciales del último récord guardado, para no tener que escribirlas cada vez
+    pub fn last_initials(&self) -> Option<&str> {
+        self.records.last().map(|r| r.initials.as_str())
+    }
+}
@@ -0,0 +1,128 @@
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+
+//Píxeles de una textura cacheados por filas para acceso rápido
+pub type TextureCache = Vec<Vec<Color>>;
+
+//Registro de texturas: cada carácter del laberinto apunta a una imagen,
+//además de las superficies especiales de piso y techo
+pub struct TextureRegistry {
+    textures: Vec<TextureCache>,
+    paths: HashMap<String, usize>,
+    walls: HashMap<char, usize>,
+    floor: Option<usize>,
+    ceiling: Option<usize>,
+}
+
+impl TextureRegistry {
+    pub fn new() -> Self {
+        TextureRegistry {
+            textures: Vec::new(),
+            paths: HashMap::new(),
+            walls: HashMap::new(),
+            floor: None,
+            ceiling: None,
+        }
+    }
+
+    //Carga un manifiesto de tema con líneas de la forma:
+    //  wall <carácter> <ruta>
+    //  floor <ruta>
+    //  ceiling <ruta>
+    //Las líneas vacías y las que empiezan con '#' se ignoran
+    pub fn load_theme(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+
+        for (line_number, line) in contents.lines().enumerate() {
+            if let Err(e) = self.apply_directive(line) {
+                println!("{}:{}: {}", path, line_number + 1, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    //Aplica una línea del manifiesto; devuelve un mensaje si no se pudo interpretar
+    pub fn apply_directive(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match keyword {
+            "wall" => {
+                let mut chars = rest.chars();
+                let cell = chars.next().ok_or("falta el carácter de la pared")?;
+                let image_path = chars.as_str().trim();
+                if image_path.is_empty() {
+                    return Err(format!("falta la imagen para la pared '{}'", cell));
+                }
+                let index = self.load_image(image_path)?;
+                self.walls.insert(cell, index);
+            },
+            "floor" => {
+                self.floor = Some(self.load_image(rest)?);
+            },
+            "ceiling" => {
+                self.ceiling = Some(self.load_image(rest)?);
+            },
+            _ => return Err(format!("directiva desconocida '{}'", keyword)),
+        }
+
+        Ok(())
+    }
+
+    //Carga una imagen una sola vez aunque varias entradas la usen
+    fn load_image(&mut self, path: &str) -> Result<usize, String> {
+        if let Some(&index) = self.paths.get(path) {
+            return Ok(index);
+        }
+
+        let image = Image::load_image(path)
+            .map_err(|e| format!("no se pudo cargar la textura {}: {}", path, e))?;
+        self.textures.push(cache_from_image(&image));
+        let index = self.textures.len() - 1;
+        self.paths.insert(path.to_string(), index);
+        Ok(index)
+    }
+
+    pub fn wall_pixel(&self, cell: char, tx: f32, ty: f32) -> Option<Color> {
+        self.walls.get(&cell).map(|&index| sample(&self.textures[index], tx, ty))
+    }
+
+    pub fn floor_pixel(&self, tx: f32, ty: f32) -> Option<Color> {
+        self.floor.map(|index| sample(&self.textures[index], tx, ty))
+    }
+
+    pub fn ceiling_pixel(&self, tx: f32, ty: f32) -> Option<Color> {
+        self.ceiling.map(|index| sample(&self.textures[index], tx, ty))
+    }
+}
+
+pub fn cache_from_image(texture: &Image) -> TextureCache {
+    let mut cache = Vec::new();
+    let mut temp_texture = texture.clone();
+
+    for y in 0..texture.height {
+        let mut row = Vec::new();
+        for x in 0..texture.width {
+            let color = temp_texture.get_color(x, y);
+            row.push(color);
+        }
+        cache.push(row);
+    }
+    cache
+}
+
+fn sample(cache: &TextureCache, tx: f32, ty: f32) -> Color {
+    if cache.is_empty() || cache[0].is_empty() {
+        return Color::MAGENTA;
+    }
+    let tex_x = ((tx * cache[0].len() as f32) as usize).min(cache[0].len() - 1);
+    let tex_y = ((ty * cache.len() as f32) as usize).min(cache.len() - 1);
+    cache[tex_y][tex_x]
+}